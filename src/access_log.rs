@@ -0,0 +1,78 @@
+//! Parser of the plain text access logs of web servers, for `--format-preset`.
+//!
+//! The lines are turned into json objects, so every feature working on json fields works on them too.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::cli::FormatPreset;
+
+// `<remote_addr> <ident> <remote_user> [<time>] "<method> <path> <protocol>" <status> <bytes_sent>` and, in the
+// combined format, `"<referer>" "<user_agent>"`. Nginx logs `-` in place of the ident.
+const COMBINED_PATTERN: &str = concat!(
+    r#"^(\S+) \S+ (\S+) \[([^\]]+)\] "(\S+) (\S+) ([^"]+)" (\d{3}) (\d+|-)"#,
+    r#"(?: "([^"]*)" "([^"]*)")?\s*$"#
+);
+
+const TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+/// Fields of the line as a json object, `None` if it doesn't follow the preset's format.
+pub fn parse(preset: FormatPreset, line: &str) -> Option<serde_json::Value> {
+    static COMBINED: OnceLock<Regex> = OnceLock::new();
+    let captures = COMBINED.get_or_init(|| Regex::new(COMBINED_PATTERN).unwrap()).captures(line)?;
+    // Nginx's default `combined` format always has the referer and user agent, Apache's common format doesn't.
+    if preset == FormatPreset::Nginx && captures.get(9).is_none() {
+        return None;
+    }
+    let time = &captures[3];
+    // As RFC 3339 so `--time-field time` can be used with the access logs too.
+    let time = chrono::DateTime::parse_from_str(time, TIME_FORMAT)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|_| time.to_string());
+    let bytes_sent: u64 = captures[8].parse().unwrap_or_default();
+    let mut fields = serde_json::json!({
+        "remote_addr": &captures[1],
+        "remote_user": &captures[2],
+        "time": time,
+        "method": &captures[4],
+        "path": &captures[5],
+        "protocol": &captures[6],
+        "status": captures[7].parse::<u16>().ok()?,
+        "bytes_sent": bytes_sent,
+    });
+    if let (Some(referer), Some(user_agent)) = (captures.get(9), captures.get(10)) {
+        fields["referer"] = referer.as_str().into();
+        fields["user_agent"] = user_agent.as_str().into();
+    }
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMBINED_LINE: &str = concat!(
+        r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "#,
+        r#""http://www.example.com/start.html" "Mozilla/4.08""#
+    );
+
+    #[test]
+    fn combined_line_fields() {
+        let fields = parse(FormatPreset::Nginx, COMBINED_LINE).unwrap();
+        assert_eq!("GET", fields["method"]);
+        assert_eq!("/apache_pb.gif", fields["path"]);
+        assert_eq!(200, fields["status"]);
+        assert_eq!(2326, fields["bytes_sent"]);
+        assert_eq!("2000-10-10T13:55:36-07:00", fields["time"]);
+        assert_eq!("Mozilla/4.08", fields["user_agent"]);
+    }
+
+    #[test]
+    fn common_format_only_for_apache() {
+        let line = r#"::1 - - [10/Oct/2000:13:55:36 +0000] "POST /login HTTP/1.1" 401 -"#;
+        assert_eq!(401, parse(FormatPreset::Apache, line).unwrap()["status"]);
+        assert_eq!(None, parse(FormatPreset::Nginx, line));
+        assert_eq!(None, parse(FormatPreset::Apache, "{\"type\":\"A\"}"));
+    }
+}
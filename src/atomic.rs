@@ -0,0 +1,49 @@
+//! Writes that never leave a partial file behind, so readers either see the previous content or the whole new one.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`. The rename is atomic as long as
+/// both are in the same filesystem, which is why the temporary file isn't created in the system temp directory.
+pub fn write(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} isn't a file path", path.display()))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+    let result = write_synced(&temp_path, contents).and_then(|()| {
+        std::fs::rename(&temp_path, path).with_context(|| format!("Failed to rename into {}", path.display()))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    // Otherwise a crash right after the rename could still leave an empty file.
+    file.sync_all().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_temp_file_left_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        std::fs::write(&path, "old").unwrap();
+        write(&path, b"new").unwrap();
+        assert_eq!("new", std::fs::read_to_string(&path).unwrap());
+        let entries: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(vec![path], entries);
+        assert!(write(&dir.path().join("missing").join("stats.json"), b"new").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! A reader running on its own thread, for `--parallel-gzip`.
+//!
+//! Gzip can't be decompressed in parallel without an index, but it can run alongside the parsing: a background thread
+//! decompresses ahead while the main thread parses what's been decompressed so far.
+
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver};
+
+const CHUNK_SIZE: usize = 256 * 1024;
+/// Chunks decompressed ahead of the parsing, bounding the memory used when the parsing is the slowest side.
+const CHUNKS_AHEAD: usize = 16;
+
+pub struct BackgroundReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    current: Vec<u8>,
+    position: usize,
+}
+
+impl BackgroundReader {
+    pub fn spawn<R: Read + Send + 'static>(mut inner: R) -> Self {
+        let (sender, chunks) = mpsc::sync_channel(CHUNKS_AHEAD);
+        std::thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let result = match inner.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(num_bytes) => {
+                    chunk.truncate(num_bytes);
+                    Ok(chunk)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => Err(error),
+            };
+            let failed = result.is_err();
+            // The receiver is gone when the reading stopped early, e.g. with `--head`.
+            if sender.send(result).is_err() || failed {
+                break;
+            }
+        });
+        Self {
+            chunks,
+            current: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for BackgroundReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.current.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    self.current = chunk?;
+                    self.position = 0;
+                }
+                // The thread is done and everything it sent has been read.
+                Err(_) => return Ok(0),
+            }
+        }
+        let num_bytes = buf.len().min(self.current.len() - self.position);
+        buf[..num_bytes].copy_from_slice(&self.current[self.position..self.position + num_bytes]);
+        self.position += num_bytes;
+        Ok(num_bytes)
+    }
+}
@@ -0,0 +1,53 @@
+//! Known-good snapshots of a previous run, to compare the current one against.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::ObjectStats;
+
+/// Leaves only the types that aren't in the baseline file, for `--new-since`.
+pub fn retain_new(path: &Path, count_map: &mut HashMap<String, ObjectStats>) -> anyhow::Result<()> {
+    let known_types = load_types(path)?;
+    count_map.retain(|object_type, _| !known_types.contains(object_type));
+    Ok(())
+}
+
+/// Types present in a baseline file. It can be the output of `--format json`, whose top-level keys are the types, or
+/// a plain array of types.
+pub fn load_types(path: &Path) -> anyhow::Result<HashSet<String>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read baseline {}", path.display()))?;
+    let baseline: serde_json::Value = serde_json::from_str(&content).context("Failed to parse baseline")?;
+    match baseline {
+        serde_json::Value::Object(types) => Ok(types.into_iter().map(|(object_type, _)| object_type).collect()),
+        serde_json::Value::Array(types) => types
+            .into_iter()
+            .map(|object_type| match object_type {
+                serde_json::Value::String(object_type) => Ok(object_type),
+                other => anyhow::bail!("Unexpected baseline type {other}"),
+            })
+            .collect(),
+        _ => anyhow::bail!("The baseline must be a json object keyed by type or an array of types"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_new_types_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        std::fs::write(&path, r#"{"A": {"count": 10, "bytes": 100}, "C": {"count": 1, "bytes": 5}}"#).unwrap();
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+        retain_new(&path, &mut count_map).unwrap();
+        assert_eq!(vec!["B"], count_map.keys().collect::<Vec<_>>());
+
+        std::fs::write(&path, r#"["A", "B"]"#).unwrap();
+        assert_eq!(HashSet::from(["A".to_string(), "B".to_string()]), load_types(&path).unwrap());
+    }
+}
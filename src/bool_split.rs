@@ -0,0 +1,69 @@
+//! Per-type counts of the values of a boolean field, e.g. how many lines of each type have `"ok": true`.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct BoolCounts {
+    #[serde(rename = "true")]
+    pub true_count: usize,
+    #[serde(rename = "false")]
+    pub false_count: usize,
+    /// Lines without the field, or where it isn't a boolean.
+    pub unknown: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BoolSplit {
+    pub field: String,
+    counts: HashMap<String, BoolCounts>,
+}
+
+impl BoolSplit {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, object_type: &str, line: &serde_json::Value) {
+        // Same as the counts, the key is only allocated the first time a type is seen.
+        let counts = match self.counts.get_mut(object_type) {
+            Some(counts) => counts,
+            None => self.counts.entry(object_type.to_string()).or_default(),
+        };
+        match line.get(&self.field).and_then(serde_json::Value::as_bool) {
+            Some(true) => counts.true_count += 1,
+            Some(false) => counts.false_count += 1,
+            None => counts.unknown += 1,
+        }
+    }
+
+    pub fn counts(&self, object_type: &str) -> BoolCounts {
+        self.counts.get(object_type).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_values_per_type() {
+        let mut sut = BoolSplit::new("ok");
+        let lines = [
+            ("A", serde_json::json!({ "ok": true })),
+            ("A", serde_json::json!({ "ok": false })),
+            ("A", serde_json::json!({ "ok": true })),
+            ("A", serde_json::json!({ "ok": "true" })),
+            ("B", serde_json::json!({ "ok": false })),
+            ("B", serde_json::json!({})),
+        ];
+        for (object_type, line) in &lines {
+            sut.record(object_type, line);
+        }
+        assert_eq!(BoolCounts { true_count: 2, false_count: 1, unknown: 1 }, sut.counts("A"));
+        assert_eq!(BoolCounts { true_count: 0, false_count: 1, unknown: 1 }, sut.counts("B"));
+        assert_eq!(BoolCounts::default(), sut.counts("C"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-type rate budgets, e.g. at most 10 `error` lines per second, for `--budget`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::ObjectStats;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Budget {
+    pub object_type: String,
+    pub max_per_second: f64,
+    /// The budget as given, e.g. `10/s`, to report it in the user's own unit.
+    pub limit: String,
+}
+
+/// Parses a `<type>:<count>/<s|m|h>` budget, e.g. `error:10/s`.
+pub fn parse_budget(budget: &str) -> Result<Budget, String> {
+    // The type may contain colons, the limit can't.
+    let (object_type, limit) = budget
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Expected <type>:<count>/<s|m|h>, got {budget}"))?;
+    let (count, unit) = limit
+        .split_once('/')
+        .ok_or_else(|| format!("Expected <count>/<s|m|h>, got {limit}"))?;
+    let count: f64 = count.parse().map_err(|_| format!("Invalid count in {budget}"))?;
+    let unit_secs = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3_600.0,
+        _ => return Err(format!("Unknown unit {unit} in {budget}, expected s, m or h")),
+    };
+    if object_type.is_empty() || !count.is_finite() || count < 0.0 {
+        return Err(format!("Invalid budget {budget}"));
+    }
+    Ok(Budget {
+        object_type: object_type.to_string(),
+        max_per_second: count / unit_secs,
+        limit: limit.to_string(),
+    })
+}
+
+/// Earliest and latest timestamps seen, the time span the rates are computed over with `--time-field`.
+#[derive(Debug, PartialEq)]
+pub struct TimeSpan {
+    pub field: String,
+    bounds: Option<(f64, f64)>,
+}
+
+impl TimeSpan {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            bounds: None,
+        }
+    }
+
+    /// Lines without a timestamp are ignored.
+    pub fn record(&mut self, line: &serde_json::Value) {
+        let Some(timestamp) = line.get(&self.field).and_then(crate::order::timestamp) else {
+            return;
+        };
+        let (first, last) = self.bounds.get_or_insert((timestamp, timestamp));
+        *first = first.min(timestamp);
+        *last = last.max(timestamp);
+    }
+
+    /// Assumes the timestamps are in seconds, like the RFC 3339 ones once converted.
+    pub fn duration(&self) -> Duration {
+        self.bounds
+            .map(|(first, last)| Duration::from_secs_f64(last - first))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BudgetCheck<'a> {
+    pub budget: &'a Budget,
+    /// Lines per second, `None` if the window is empty.
+    pub rate: Option<f64>,
+}
+
+impl BudgetCheck<'_> {
+    pub fn exceeded(&self) -> bool {
+        self.rate.is_some_and(|rate| rate > self.budget.max_per_second)
+    }
+}
+
+impl fmt::Display for BudgetCheck<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Budget {} {}: ", self.budget.object_type, self.budget.limit)?;
+        match self.rate {
+            Some(rate) => write!(f, "{rate:.2}/s")?,
+            None => write!(f, "-")?,
+        }
+        if self.exceeded() {
+            write!(f, " (exceeded)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Rate of each budget's type over `window`, in the order the budgets were given.
+pub fn check<'a>(
+    budgets: &'a [Budget],
+    count_map: &HashMap<String, ObjectStats>,
+    window: Duration,
+) -> Vec<BudgetCheck<'a>> {
+    let window_secs = window.as_secs_f64();
+    budgets
+        .iter()
+        .map(|budget| {
+            let count = count_map.get(&budget.object_type).map_or(0, |object_stats| object_stats.count);
+            BudgetCheck {
+                budget,
+                rate: (window_secs > 0.0).then(|| count as f64 / window_secs),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_over_budget_is_flagged() {
+        let budgets = vec![parse_budget("error:10/s").unwrap(), parse_budget("warn:600/m").unwrap()];
+        let mut count_map = HashMap::new();
+        count_map.insert("error".to_string(), ObjectStats { count: 30, bytes: 300, content_hash: 0 });
+        count_map.insert("warn".to_string(), ObjectStats { count: 10, bytes: 100, content_hash: 0 });
+        let mut time_span = TimeSpan::new("ts");
+        for ts in ["2024-01-02T15:00:01Z", "2024-01-02T15:00:00Z", "2024-01-02T15:00:02Z"] {
+            time_span.record(&serde_json::json!({ "ts": ts }));
+        }
+        let checks = check(&budgets, &count_map, time_span.duration());
+        assert_eq!(Some(15.0), checks[0].rate);
+        assert!(checks[0].exceeded());
+        assert_eq!("Budget error 10/s: 15.00/s (exceeded)", checks[0].to_string());
+        assert!(!checks[1].exceeded());
+        assert!(!check(&budgets, &count_map, Duration::ZERO)[0].exceeded());
+    }
+
+    #[test]
+    fn budget_parsing() {
+        assert_eq!(0.5, parse_budget("GET /a:30/m").unwrap().max_per_second);
+        assert_eq!("GET /a", parse_budget("GET /a:30/m").unwrap().object_type);
+        assert!(parse_budget("error:10").is_err());
+        assert!(parse_budget("error:10/d").is_err());
+        assert!(parse_budget(":10/s").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Cache of the counts of unchanged inputs, for `--cache-dir`.
+//!
+//! A run is cached as a whole: the key covers the checksum and modification time of every input, and the options that
+//! change what's counted. The output options don't, so the same counts can be rendered in every format.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::cli::Args;
+use crate::{LogStats, ObjectStats};
+
+// Generic so storing can borrow the counts and loading can own them.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry<M> {
+    file_len_bytes: u64,
+    count_map: M,
+}
+
+/// Cache file of these inputs and options, `None` if the run can't be cached.
+///
+/// Only the plain counts are cached, so runs with accumulators of their own, or with stdin, are always processed.
+pub fn entry_path(cache_dir: &Path, inputs: &[String], args: &Args) -> anyhow::Result<Option<PathBuf>> {
+    let has_accumulators = args.histogram.is_some()
+        || args.check_order
+        || args.transitions
+        || args.by_file_date
+        || args.heavy_hitters.is_some()
+        || args.content_hash
+        || args.split_bool.is_some()
+        || args.unique_lines
+        || args.extremes
+        || args.detect_schema_change
+        || !args.budgets.is_empty();
+    if has_accumulators || inputs.iter().any(|input| input == crate::STDIN_INPUT) {
+        return Ok(None);
+    }
+    // `DefaultHasher` may change between builds, which only costs a cache miss.
+    let mut hasher = DefaultHasher::new();
+    counting_options(args).hash(&mut hasher);
+    // The countries of the same IPs change with the database, which is usually updated in place.
+    if let Some(geo_db) = &args.geo_db {
+        let metadata = std::fs::metadata(geo_db).ok();
+        metadata.as_ref().map(|metadata| (metadata.len(), metadata.modified().ok())).hash(&mut hasher);
+    }
+    for input in inputs {
+        let mut file = std::fs::File::open(input).with_context(|| format!("Failed to open {input}"))?;
+        let modified = file.metadata().and_then(|metadata| metadata.modified()).ok();
+        input.hash(&mut hasher);
+        modified.hash(&mut hasher);
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let num_bytes = file.read(&mut chunk).with_context(|| format!("Failed to read {input}"))?;
+            if num_bytes == 0 {
+                break;
+            }
+            chunk[..num_bytes].hash(&mut hasher);
+        }
+    }
+    Ok(Some(cache_dir.join(format!("{:016x}.json", hasher.finish()))))
+}
+
+/// Options that change the counts or the size of the inputs. The accumulators aren't cached, so their options are
+/// left out too.
+fn counting_options(args: &Args) -> String {
+    let key = (&args.key, args.format_preset, &args.token_field, &args.time_prefix, &args.levels);
+    let key = (key, &args.key_join, &args.key_separator, &args.geo_field, &args.geo_db);
+    let normalization = (args.collapse_uuids, args.merge_plurals, args.auto_collapse_after);
+    let filters = (&args.contains, args.ignore_case, args.skip_header_bytes, &args.root_path, args.head);
+    let reading = (args.reverse_read, args.resume_from, args.strict_utf8, args.continue_on_invalid_utf8);
+    let sampling = (args.sample_rate, args.seed);
+    format!("{:?}", (key, normalization, filters, reading, sampling))
+}
+
+/// The cached stats, `None` on a miss. An unreadable entry is a miss too, it's overwritten by the fresh stats.
+pub fn load(path: &Path, args: &Args) -> Option<LogStats> {
+    let content = std::fs::read(path).ok()?;
+    let entry: CacheEntry<HashMap<String, ObjectStats>> = serde_json::from_slice(&content).ok()?;
+    Some(LogStats {
+        file_len_bytes: entry.file_len_bytes,
+        count_map: entry.count_map,
+        // Not an accumulator, but it changes how the cached counts are sorted.
+        level_order: args.levels.is_some(),
+        ..Default::default()
+    })
+}
+
+pub fn store(path: &Path, stats: &LogStats) -> anyhow::Result<()> {
+    if let Some(cache_dir) = path.parent() {
+        std::fs::create_dir_all(cache_dir).context("Failed to create cache directory")?;
+    }
+    let entry = CacheEntry {
+        file_len_bytes: stats.file_len_bytes,
+        count_map: &stats.count_map,
+    };
+    crate::atomic::write(path, &serde_json::to_vec(&entry)?).context("Failed to write cache entry")
+}
@@ -0,0 +1,67 @@
+//! Restartable processing: seeking to a byte offset and periodically saving how far we got.
+
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// How many lines are processed between two checkpoint writes.
+const WRITE_INTERVAL_LINES: usize = 100_000;
+
+/// Seeks to `offset` and skips the rest of the line it falls into, unless it already is the start of a line.
+/// Returns the offset of the first line that will be processed.
+pub fn seek_to_line<R: BufRead + Seek>(reader: &mut R, offset: u64) -> anyhow::Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    // Starting one byte before lets a single `read_until` handle both cases: if that byte is a `\n` the offset is a line
+    // boundary and only the `\n` is skipped, otherwise we skip up to the end of the partial line.
+    reader.seek(SeekFrom::Start(offset - 1)).context("Failed to seek input")?;
+    let mut skipped = Vec::new();
+    let skipped_bytes = reader.read_until(b'\n', &mut skipped).context("Failed to read line")?;
+    Ok(offset - 1 + skipped_bytes as u64)
+}
+
+/// Same as `seek_to_line` for the inputs that can't seek, like stdin or decompressed ones, by reading up to `offset`.
+pub fn skip_to_line<R: BufRead>(reader: &mut R, offset: u64) -> anyhow::Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    let copied =
+        std::io::copy(&mut reader.by_ref().take(offset - 1), &mut std::io::sink()).context("Failed to skip input")?;
+    let mut skipped = Vec::new();
+    let skipped_bytes = reader.read_until(b'\n', &mut skipped).context("Failed to read line")?;
+    Ok(copied + skipped_bytes as u64)
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    offset: u64,
+    lines_since_write: usize,
+}
+
+impl Checkpoint {
+    pub fn new(path: &Path, offset: u64) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offset,
+            lines_since_write: 0,
+        }
+    }
+
+    /// Accounts for a processed line, writing the checkpoint every `WRITE_INTERVAL_LINES`.
+    pub fn advance(&mut self, num_bytes: usize) -> anyhow::Result<()> {
+        self.offset += num_bytes as u64;
+        self.lines_since_write += 1;
+        if self.lines_since_write == WRITE_INTERVAL_LINES {
+            self.write()?;
+            self.lines_since_write = 0;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        // A crash halfway through would otherwise leave a checkpoint that can't be resumed from.
+        crate::atomic::write(&self.path, self.offset.to_string().as_bytes()).context("Failed to write checkpoint")
+    }
+}
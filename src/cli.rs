@@ -0,0 +1,489 @@
+use std::borrow::Cow;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::budget::{self, Budget};
+use crate::sample;
+use crate::severity::{self, SeverityRule, SeverityThreshold};
+use crate::token::{self, TimePrefix, TokenField};
+
+/// Field counted by default, the only one the fast path of the parser is able to extract.
+const DEFAULT_KEY: &str = "type";
+/// Default `--key` of the access logs, which have no `type` field.
+const DEFAULT_PRESET_KEY: &str = "status";
+
+/// Counts the lines of a json log file grouped by their `type` field, or the one set with `--key`.
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Args {
+    /// Files to process. Their counts are aggregated; `-` reads from stdin.
+    pub files: Vec<String>,
+
+    /// File to process when no positional files are given. Can be repeated.
+    #[arg(long)]
+    pub input: Vec<String>,
+
+    /// Inputs that are directories are walked and every file in them is processed.
+    #[arg(long)]
+    pub recursive: bool,
+
+    /// With `--recursive`, only the files with one of these extensions are processed, e.g. `log,json`.
+    #[arg(long, value_delimiter = ',', requires = "recursive")]
+    pub ext: Vec<String>,
+
+    /// Config file to load. Use `-` to read it from stdin. The defaults are used if it's the default one and it doesn't
+    /// exist.
+    #[arg(long, default_value = crate::CONFIG_FILE)]
+    pub config: String,
+
+    /// Field the lines are counted by, `type` by default or `status` with a `--format-preset`. Strings, numbers and
+    /// booleans can be used as keys.
+    #[arg(long)]
+    pub key: Option<String>,
+
+    /// Parses the lines as the access logs of this web server instead of json. Their fields (`method`, `path`,
+    /// `status`, `remote_addr`, ...) can be used as `--key`, lines in another format are skipped.
+    #[arg(long, value_enum, conflicts_with = "root_path")]
+    pub format_preset: Option<FormatPreset>,
+
+    /// `<field>:<index>` to count by the word at that position of a text field instead of `--key`, e.g. `message:0`
+    /// for the first word of the `message`. Lines with fewer words are skipped.
+    #[arg(long, value_parser = token::parse_token_field, conflicts_with = "key")]
+    pub token_field: Option<TokenField>,
+
+    /// `<field>:<len>` to count by the first characters of a timestamp field instead of `--key`, e.g. `time:13` for the
+    /// hour of RFC 3339 timestamps. The timestamps aren't parsed, lines with shorter timestamps are skipped.
+    #[arg(long, value_parser = token::parse_time_prefix, conflicts_with_all = ["key", "token_field"])]
+    pub time_prefix: Option<TimePrefix>,
+
+    /// Only counts the lines containing this text. It's checked before parsing, so it's a cheap way to narrow down big
+    /// inputs. The other lines aren't considered malformed, they are just ignored. With `--root-path` it's checked on
+    /// the compact json of each element.
+    #[arg(long)]
+    pub contains: Option<String>,
+
+    /// Makes `--contains` case insensitive.
+    #[arg(long, requires = "contains")]
+    pub ignore_case: bool,
+
+    /// Counts by the values of these fields joined with `--key-separator` instead of `--key`, e.g. `method,status`.
+    /// Lines missing any of them are skipped.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["key", "token_field", "time_prefix"])]
+    pub key_join: Vec<String>,
+
+    /// Separator of the `--key-join` values.
+    #[arg(long, default_value = " ", requires = "key_join")]
+    pub key_separator: String,
+
+    /// Counts by the country of the IP address in this field instead of `--key`, looked up in the `--geo-db`. Lines
+    /// whose IP isn't found are skipped.
+    #[arg(long, requires = "geo_db", conflicts_with_all = ["key", "token_field", "time_prefix", "key_join"])]
+    pub geo_field: Option<String>,
+
+    /// Counts by the log level in this field, `level` by default, instead of `--key`, and sorts the types from TRACE to
+    /// FATAL with the unknown levels at the end. The field has to be given as `--levels=<FIELD>`.
+    #[arg(
+        long,
+        value_name = "FIELD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "level",
+        conflicts_with_all = ["key", "token_field", "time_prefix", "key_join", "geo_field"]
+    )]
+    pub levels: Option<String>,
+
+    /// MaxMind country database (`.mmdb`), e.g. GeoLite2-Country, used by `--geo-field`.
+    #[arg(long, requires = "geo_field")]
+    pub geo_db: Option<PathBuf>,
+
+    /// Skips this many bytes at the start of each input, and the rest of the line they end in, e.g. to leave out a
+    /// header that isn't json. For gzip inputs they are bytes of the decompressed content.
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "root_path")]
+    pub skip_header_bytes: u64,
+
+    /// Warns if a file grew while it was processed, which usually means it's still being written.
+    #[arg(long)]
+    pub warn_if_growing: bool,
+
+    /// Only counts this random fraction of the lines, or of the `--root-path` elements, and scales the counts and sizes
+    /// up to estimate the totals. The other accumulators, like `--histogram`, aren't scaled.
+    #[arg(long, value_name = "RATE", value_parser = sample::parse_rate)]
+    pub sample_rate: Option<f64>,
+
+    /// Adds the approximate 95% confidence interval of each `--sample-rate` estimated count.
+    #[arg(long, requires = "sample_rate")]
+    pub with_ci: bool,
+
+    /// Seed of the `--sample-rate` random generator, the same seed samples the same lines of the same input.
+    #[arg(long, default_value_t = 0, requires = "sample_rate")]
+    pub seed: u64,
+
+    /// Validates that the lines are UTF-8, failing with the offset of the first invalid byte and the bytes around it.
+    #[arg(long, conflicts_with_all = ["reverse_read", "root_path"])]
+    pub strict_utf8: bool,
+
+    /// Only warns about the `--strict-utf8` invalid bytes, which are replaced with `U+FFFD`, and keeps counting.
+    #[arg(long, requires = "strict_utf8")]
+    pub continue_on_invalid_utf8: bool,
+
+    /// Stops after reading this many lines, across all the inputs.
+    #[arg(long, value_name = "N")]
+    pub head: Option<usize>,
+
+    /// Reads the files from their last line to their first, so `--head` takes the newest lines. The counts of a whole
+    /// file are the same in both directions.
+    #[arg(long, conflicts_with_all = ["root_path", "resume_from", "checkpoint", "progress"])]
+    pub reverse_read: bool,
+
+    /// Decompresses the gzip inputs on a background thread, so the decompression and the parsing run at the same time.
+    #[arg(long)]
+    pub parallel_gzip: bool,
+
+    /// Parses each input as a single json document and counts the elements of the array at this json pointer,
+    /// e.g. `/data/items`. The document is parsed whole, so it can't be resumed from an offset nor checkpointed.
+    #[arg(long, conflicts_with_all = ["resume_from", "checkpoint"])]
+    pub root_path: Option<String>,
+
+    /// Prints a commented config template with the default values and exits.
+    #[arg(long)]
+    pub print_default_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Output format of the stats.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+
+    /// Writes the stats to this file instead of stdout. It's replaced atomically, so it's never seen half written.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Wraps the types longer than this many characters over several lines of the table.
+    #[arg(long, value_name = "N", conflicts_with = "truncate_keys")]
+    pub wrap_keys: Option<NonZeroUsize>,
+
+    /// Truncates the types longer than this many characters in the table, marking the cut with an ellipsis.
+    #[arg(long, value_name = "N")]
+    pub truncate_keys: Option<NonZeroUsize>,
+
+    /// Also copies the stats to the system clipboard, in the chosen `--format`. On Linux a background process keeps
+    /// them in the clipboard after the run exits, until something else is copied.
+    #[arg(long)]
+    pub clipboard: bool,
+
+    /// Extra columns to add to the table, comma separated.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Measurement name of the points of `--format influx`.
+    #[arg(long, default_value = "log_counts")]
+    pub measurement: String,
+
+    /// Only outputs the types that aren't in this baseline, e.g. the `--format json` output of a known-good run.
+    #[arg(long, value_name = "BASELINE")]
+    pub new_since: Option<PathBuf>,
+
+    /// Comma separated Kafka brokers to publish a message per type to, in `--kafka-topic`.
+    #[cfg(feature = "kafka")]
+    #[arg(long, requires = "kafka_topic")]
+    pub kafka_brokers: Option<String>,
+
+    /// Kafka topic the messages are published to.
+    #[cfg(feature = "kafka")]
+    #[arg(long, requires = "kafka_brokers")]
+    pub kafka_topic: Option<String>,
+
+    /// `<severity>:<regex>` rule classifying the types whose whole name matches the regex, e.g.
+    /// `error:.*(error|fail).*`. Can be repeated, the first matching rule wins. Adds a severity column to the output.
+    #[arg(long = "severity-rule", value_parser = severity::parse_rule)]
+    pub severity_rules: Vec<SeverityRule>,
+
+    /// `<severity>:<max count>` thresholds, e.g. `error:0,warn:100`. The run fails if the types of a severity add up to
+    /// more lines than its threshold.
+    #[arg(long, value_delimiter = ',', value_parser = severity::parse_threshold, requires = "severity_rules")]
+    pub severity_threshold: Vec<SeverityThreshold>,
+
+    /// `<type>:<count>/<s|m|h>` maximum rate of a type, e.g. `error:10/s`. Can be repeated. The rate is computed over
+    /// the span of the `--time-field` timestamps if set, over the processing time otherwise.
+    #[arg(long = "budget", value_parser = budget::parse_budget)]
+    pub budgets: Vec<Budget>,
+
+    /// Fails the run if any `--budget` is exceeded.
+    #[arg(long, requires = "budgets")]
+    pub fail_on_budget: bool,
+
+    /// Adds the counts of each type by the value of this boolean field: true, false, or unknown when it's missing or
+    /// isn't a boolean.
+    #[arg(long, value_name = "FIELD")]
+    pub split_bool: Option<String>,
+
+    /// Reports the line ranges where the top-level keys present in most lines change, e.g. after a producer upgrade.
+    /// The line numbers are counted across all the inputs.
+    #[arg(long)]
+    pub detect_schema_change: bool,
+
+    /// Lines compared at a time by `--detect-schema-change`. Keys in less than half of them are considered optional.
+    #[arg(long, default_value = "1000", value_name = "LINES", requires = "detect_schema_change")]
+    pub schema_window: NonZeroUsize,
+
+    /// Reports the longest and the shortest counted lines, with their type and size.
+    #[arg(long)]
+    pub extremes: bool,
+
+    /// Adds the count of distinct lines of each type, where repeated lines are only counted the first time.
+    #[arg(long, conflicts_with = "heavy_hitters")]
+    pub unique_lines: bool,
+
+    /// Marks the types whose count is further than `--outlier-sigmas` standard deviations from the mean count.
+    #[arg(long)]
+    pub flag_outliers: bool,
+
+    /// How many standard deviations away from the mean a count has to be to be flagged by `--flag-outliers`.
+    #[arg(long, default_value_t = 3.0, requires = "flag_outliers")]
+    pub outlier_sigmas: f64,
+
+    /// Fails the run if `--flag-outliers` flags any type.
+    #[arg(long, requires = "flag_outliers")]
+    pub fail_on_outliers: bool,
+
+    /// Format of the report printed on stderr when a threshold, budget or outlier check fails the run. Plain text if
+    /// not set.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub error_report: Option<ErrorReport>,
+
+    /// Caches the counts in this directory, and reuses them when the inputs and the options that change the counts are
+    /// the same as in a previous run, whatever the `--format`. Runs reading stdin or with accumulators other than the
+    /// counts, like `--histogram`, aren't cached.
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Processes the inputs even if there are cached counts for them, and doesn't cache the new ones.
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Appends a json record with the counts of this run to this json-lines file.
+    #[arg(long)]
+    pub history: Option<PathBuf>,
+
+    /// Adds the change of each type against its average count over this many of the last runs in the `--history`.
+    #[arg(long, value_name = "RUNS", requires = "history")]
+    pub trend: Option<NonZeroUsize>,
+
+    /// Displays the counts of a `--format snapshot` blob instead of processing any input.
+    #[arg(long, value_name = "BLOB")]
+    pub load_snapshot: Option<String>,
+
+    /// Adds an order independent checksum of the lines of each type, to check two inputs have the same content.
+    #[arg(long)]
+    pub content_hash: bool,
+
+    /// Colors each type with a stable color derived from its name, the same one on every run. Only the table and
+    /// `--format vega` have colors.
+    #[arg(long)]
+    pub color_by_type: bool,
+
+    /// Replaces UUIDs and long hex ids in the counted value with a placeholder, merging all their variants.
+    #[arg(long)]
+    pub collapse_uuids: bool,
+
+    /// Merges plural and singular variants of the counted value, e.g. `users` and `user`, under the singular form.
+    #[arg(long)]
+    pub merge_plurals: bool,
+
+    /// Counts the lines by ranges of this numeric field instead of by type. The ranges are set with `--bins`. They are
+    /// always rendered as a table.
+    #[arg(long, requires = "bins", conflicts_with = "format")]
+    pub histogram: Option<String>,
+
+    /// Comma separated, increasing edges of the `--histogram` bins, e.g. `0,10,50,100,500`.
+    #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+    pub bins: Vec<f64>,
+
+    /// Byte offset of the input file to resume from. Processing starts at the first line boundary after the offset.
+    #[arg(long)]
+    pub resume_from: Option<u64>,
+
+    /// File where the byte offset processed so far is periodically written, to be used with `--resume-from`. Requires a
+    /// single input file.
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Reports the progress and ETA of each input file on stderr. For gzip inputs it's based on the compressed bytes.
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Shows the number of lines read so far in the terminal title, if stderr is a terminal.
+    #[arg(long)]
+    pub title_progress: bool,
+
+    /// Shows the `--title-progress` even if stderr isn't a terminal.
+    #[arg(long, hide = true, requires = "title_progress")]
+    pub force_tty: bool,
+
+    /// After counting, emits a tracing span per type with its count and bytes as attributes.
+    #[arg(long)]
+    pub spans: bool,
+
+    /// Field with the timestamp of each line, either a number or an RFC 3339 string.
+    #[arg(long)]
+    pub time_field: Option<String>,
+
+    /// Reports the lines whose `--time-field` is older than the previous line's.
+    #[arg(long, requires = "time_field")]
+    pub check_order: bool,
+
+    /// Reports the Gini coefficient of the counts per type: near 0 if they're even, near 1 if one type dominates.
+    #[arg(long)]
+    pub gini: bool,
+
+    /// Also counts the transitions between the types of consecutive lines.
+    #[arg(long)]
+    pub transitions: bool,
+
+    /// With `--transitions`, a line that isn't counted breaks the sequence instead of being ignored.
+    #[arg(long, requires = "transitions")]
+    pub reset_on_skip: bool,
+
+    /// Buckets the counts by the modification day of the file they come from, instead of merging all the files. They
+    /// are always rendered as a table.
+    #[arg(long, conflicts_with = "format")]
+    pub by_file_date: bool,
+
+    /// With `--by-file-date`, shows the running total of each type up to each date instead of the count of the date.
+    #[arg(long, requires = "by_file_date")]
+    pub cumulative: bool,
+
+    /// Reports the approximate top K types using only K counters, for inputs with too many distinct types to count
+    /// them all exactly. They are always rendered as a table.
+    #[arg(long, value_name = "K", conflicts_with = "format")]
+    pub heavy_hitters: Option<usize>,
+
+    /// Once this many distinct types are counted, the lines of any new type are counted together as `(other)`, to keep
+    /// the memory and the output bounded. The types seen before are still counted exactly.
+    #[arg(long, value_name = "N", conflicts_with = "heavy_hitters")]
+    pub auto_collapse_after: Option<NonZeroUsize>,
+
+    /// Fails if the process memory grows beyond `--memory-bound-mb` while streaming, besides what the distinct types
+    /// take. Meant to catch regressions that buffer the whole input.
+    #[arg(long)]
+    pub assert_constant_memory: bool,
+
+    /// Allowed memory growth in MB for `--assert-constant-memory`.
+    #[arg(long, default_value_t = 64)]
+    pub memory_bound_mb: u64,
+}
+
+/// Commands run instead of counting the inputs, none of the other options can be given with them.
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Counts a generated log with known counts and prints its table, then PASS if the counts match and FAIL
+    /// otherwise.
+    #[command(long_flag = "self-test")]
+    SelfTest,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable table.
+    Table,
+    /// Minimal Vega-Lite bar chart spec with the counts embedded under `data.values`.
+    Vega,
+    /// Json object with the counters of each type, keyed by type. Can be used as a `--new-since` baseline.
+    Json,
+    /// InfluxDB line protocol, one point per type.
+    Influx,
+    /// The table without borders nor colors, with the columns aligned with spaces.
+    Plain,
+    /// Markdown table with the same columns as the table, to paste in issues and docs.
+    Markdown,
+    /// Fixed width plain text sorted by type, without anything that changes between runs. Meant to be diffed.
+    Canonical,
+    /// `type=count` lines sorted by type, as a Java `.properties` file.
+    Properties,
+    /// `WC_<TYPE>=count` shell variable assignments, to be `eval`ed.
+    Env,
+    /// Opaque single line blob with the counts, to be displayed later with `--load-snapshot`.
+    Snapshot,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorReport {
+    /// Single line json object with a `failures` array, each with the `gate`, a `message` and its `details`.
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatPreset {
+    /// Nginx's default `combined` format.
+    Nginx,
+    /// Apache's combined format, or the common one without the referer and user agent.
+    Apache,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// Bytes per second of each type over the processing time.
+    #[value(name = "byte_rate")]
+    ByteRate,
+}
+
+impl Args {
+    /// Whether any enabled feature looks at fields other than `type`, so the lines have to be fully deserialized.
+    pub fn needs_json_value(&self) -> bool {
+        self.key_field() != DEFAULT_KEY
+            || self.histogram.is_some()
+            || self.check_order
+            || self.token_field.is_some()
+            || self.time_prefix.is_some()
+            || !self.key_join.is_empty()
+            || self.geo_field.is_some()
+            || self.split_bool.is_some()
+            || self.detect_schema_change
+            || self.levels.is_some()
+            || (self.time_field.is_some() && !self.budgets.is_empty())
+    }
+
+    /// Field counted by `--key`, the default one depends on the `--format-preset`.
+    pub fn key_field(&self) -> &str {
+        match (&self.key, self.format_preset) {
+            (Some(key), _) => key,
+            (None, Some(_)) => DEFAULT_PRESET_KEY,
+            (None, None) => DEFAULT_KEY,
+        }
+    }
+
+    /// Name of what the lines are counted by, the field of whichever of the key options is set, e.g. for the tag of
+    /// the influx points. The `--key-join` fields are joined with `_`.
+    pub fn key_name(&self) -> Cow<'_, str> {
+        if let Some(field) = &self.geo_field {
+            return Cow::Owned(format!("{field}_country"));
+        }
+        if let Some(token_field) = &self.token_field {
+            return Cow::Borrowed(&token_field.field);
+        }
+        if let Some(time_prefix) = &self.time_prefix {
+            return Cow::Borrowed(&time_prefix.field);
+        }
+        if let Some(field) = &self.levels {
+            return Cow::Borrowed(field);
+        }
+        if !self.key_join.is_empty() {
+            return Cow::Owned(self.key_join.join("_"));
+        }
+        Cow::Borrowed(self.key_field())
+    }
+
+    /// Inputs to process: the positional files, then `--input`, then the config `input_file` as last resort.
+    pub fn inputs(&self, config_input: &str) -> Vec<String> {
+        if !self.files.is_empty() {
+            self.files.clone()
+        } else if !self.input.is_empty() {
+            self.input.clone()
+        } else {
+            vec![config_input.to_string()]
+        }
+    }
+}
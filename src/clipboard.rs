@@ -0,0 +1,56 @@
+//! Copying the rendered stats to the system clipboard, for `--clipboard`.
+
+use anyhow::Context;
+
+/// Set in the environment of the process that keeps the copied text in the clipboard after the run exits.
+#[cfg(target_os = "linux")]
+const HOLDER_ENV: &str = "WORD_COUNTER_CLIPBOARD_HOLDER";
+
+#[cfg(not(target_os = "linux"))]
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("No clipboard available, is this a headless environment?")?;
+    clipboard.set_text(text).context("Failed to copy the stats to the clipboard")
+}
+
+/// The X11 and Wayland clipboards are served by the process that set them, so the text would be gone as soon as this
+/// one exits. It's handed to a copy of this binary in the background instead, which serves it until it's replaced.
+#[cfg(target_os = "linux")]
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    // Checked here, the holder can't report that there is no clipboard.
+    arboard::Clipboard::new().context("No clipboard available, is this a headless environment?")?;
+    let mut holder = Command::new(std::env::current_exe().context("Failed to find the word-counter binary")?)
+        .env(HOLDER_ENV, "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .current_dir("/")
+        .spawn()
+        .context("Failed to start the clipboard holder")?;
+    let mut stdin = holder.stdin.take().expect("The holder stdin is piped");
+    stdin.write_all(text.as_bytes()).context("Failed to copy the stats to the clipboard")
+}
+
+/// Serves the text on stdin as the clipboard content until something else is copied, if this is the holder process
+/// started by `copy`. Returns whether it was.
+#[cfg(target_os = "linux")]
+pub fn hold_if_holder() -> anyhow::Result<bool> {
+    use std::io::Read;
+
+    use arboard::SetExtLinux;
+
+    if std::env::var_os(HOLDER_ENV).is_none() {
+        return Ok(false);
+    }
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+    arboard::Clipboard::new()?.set().wait().text(text)?;
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn hold_if_holder() -> anyhow::Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,80 @@
+//! Deterministic colors per type, so the same type looks the same across runs, tables and charts.
+
+// The 6x6x6 color cube of the 256-color palette, skipping the pure black and white corners so every type stays readable.
+const FIRST_CUBE_COLOR: u8 = 17;
+const LAST_CUBE_COLOR: u8 = 230;
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Maps a type to a 256-color code.
+///
+/// `std`'s hasher is randomly seeded per process, so we use FNV-1a to get the same color on every run.
+pub fn type_color(object_type: &str) -> u8 {
+    let span = (LAST_CUBE_COLOR - FIRST_CUBE_COLOR) as u64 + 1;
+    FIRST_CUBE_COLOR + (fnv1a(object_type.as_bytes()) % span) as u8
+}
+
+/// Hex RGB value of a 256-color code, for outputs that don't understand terminal colors.
+pub fn ansi256_to_hex(code: u8) -> String {
+    let (r, g, b) = match code {
+        16..=231 => {
+            let index = (code - 16) as usize;
+            (CUBE_LEVELS[index / 36], CUBE_LEVELS[(index / 6) % 6], CUBE_LEVELS[index % 6])
+        }
+        232..=255 => {
+            let level = 8 + (code - 232) * 10;
+            (level, level, level)
+        }
+        // The first 16 colors depend on the terminal theme, these are the xterm defaults.
+        _ => {
+            const BASIC: [(u8, u8, u8); 16] = [
+                (0, 0, 0),
+                (128, 0, 0),
+                (0, 128, 0),
+                (128, 128, 0),
+                (0, 0, 128),
+                (128, 0, 128),
+                (0, 128, 128),
+                (192, 192, 192),
+                (128, 128, 128),
+                (255, 0, 0),
+                (0, 255, 0),
+                (255, 255, 0),
+                (0, 0, 255),
+                (255, 0, 255),
+                (0, 255, 255),
+                (255, 255, 255),
+            ];
+            BASIC[code as usize]
+        }
+    };
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Stable 64-bit hash, also used for the `--content-hash` checksums.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes
+        .iter()
+        .fold(OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_type_same_color() {
+        assert_eq!(type_color("nulla"), type_color("nulla"));
+        // Pinned so a change in the hashing, which would recolor every chart, doesn't go unnoticed.
+        assert_eq!(110, type_color("nulla"));
+        assert!((FIRST_CUBE_COLOR..=LAST_CUBE_COLOR).contains(&type_color("dolore")));
+    }
+
+    #[test]
+    fn cube_colors_to_hex() {
+        assert_eq!("#000000", ansi256_to_hex(16));
+        assert_eq!("#ffffff", ansi256_to_hex(231));
+        assert_eq!("#5f87af", ansi256_to_hex(67));
+    }
+}
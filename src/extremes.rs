@@ -0,0 +1,66 @@
+//! The longest and shortest counted lines, kept as examples for `--extremes`.
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Example {
+    pub object_type: String,
+    /// The line without its line ending.
+    pub line: String,
+    /// Size of the line as counted, including its line ending.
+    pub bytes: usize,
+}
+
+impl Example {
+    fn replace(&mut self, object_type: &str, line: &str, bytes: usize) {
+        // Reusing the buffers, a new extreme can be found often at the start of an input.
+        self.object_type.clear();
+        self.object_type.push_str(object_type);
+        self.line.clear();
+        self.line.push_str(line.trim_end_matches(['\n', '\r']));
+        self.bytes = bytes;
+    }
+}
+
+/// The first line of the smallest and of the largest size, only those two are kept in memory.
+#[derive(Debug, Default, PartialEq)]
+pub struct Extremes {
+    pub shortest: Option<Example>,
+    pub longest: Option<Example>,
+}
+
+impl Extremes {
+    pub fn record(&mut self, object_type: &str, line: &str, bytes: usize) {
+        if self.shortest.as_ref().is_none_or(|shortest| bytes < shortest.bytes) {
+            self.shortest.get_or_insert_with(Example::default).replace(object_type, line, bytes);
+        }
+        if self.longest.as_ref().is_none_or(|longest| bytes > longest.bytes) {
+            self.longest.get_or_insert_with(Example::default).replace(object_type, line, bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_shortest_and_longest_lines() {
+        let mut sut = Extremes::default();
+        let lines = [
+            ("A", "{\"type\":\"A\",\"id\":10}\n"),
+            ("B", "{\"type\":\"B\"}\n"),
+            ("A", "{\"type\":\"A\",\"message\":\"a long one\"}\n"),
+            ("C", "{\"type\":\"C\"}\n"),
+            ("A", "{\"type\":\"A\",\"id\":11}"),
+        ];
+        for (object_type, line) in lines {
+            sut.record(object_type, line, line.len());
+        }
+        let shortest = sut.shortest.unwrap();
+        assert_eq!("B", shortest.object_type);
+        assert_eq!("{\"type\":\"B\"}", shortest.line);
+        assert_eq!(13, shortest.bytes);
+        let longest = sut.longest.unwrap();
+        assert_eq!("{\"type\":\"A\",\"message\":\"a long one\"}", longest.line);
+        assert_eq!(36, longest.bytes);
+    }
+}
@@ -0,0 +1,96 @@
+//! Counts bucketed by the modification date of the file they come from, for `--by-file-date`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+/// Bucket of the lines read from stdin, which has no modification date.
+pub const STDIN_BUCKET: &str = "stdin";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct FileDates {
+    current: String,
+    /// Counts per type of each day, sorted by date.
+    pub counts: BTreeMap<String, HashMap<String, usize>>,
+}
+
+impl FileDates {
+    /// Sets the bucket of the lines of the file about to be processed.
+    pub fn start_file(&mut self, modified: SystemTime) {
+        self.current = DateTime::<Utc>::from(modified).format("%Y-%m-%d").to_string();
+    }
+
+    pub fn start_stdin(&mut self) {
+        self.current = STDIN_BUCKET.to_string();
+    }
+
+    pub fn record(&mut self, object_type: &str) {
+        let type_counts = match self.counts.get_mut(&self.current) {
+            Some(type_counts) => type_counts,
+            None => self.counts.entry(self.current.clone()).or_default(),
+        };
+        match type_counts.get_mut(object_type) {
+            Some(count) => *count += 1,
+            None => {
+                type_counts.insert(object_type.to_string(), 1);
+            }
+        }
+    }
+
+    /// Running totals: each day has the counts of that day plus all the previous ones, including the types that were
+    /// only seen before.
+    pub fn cumulative(&self) -> FileDates {
+        let mut totals: HashMap<String, usize> = HashMap::new();
+        let mut counts = BTreeMap::new();
+        for (date, type_counts) in &self.counts {
+            for (object_type, count) in type_counts {
+                *totals.entry(object_type.clone()).or_default() += count;
+            }
+            counts.insert(date.clone(), totals.clone());
+        }
+        FileDates {
+            current: self.current.clone(),
+            counts,
+        }
+    }
+
+    /// `(date, type, count)` sorted by date, then by descending count and type.
+    pub fn sorted(&self) -> Vec<(&str, &str, usize)> {
+        let mut rows = vec![];
+        for (date, type_counts) in &self.counts {
+            let mut type_counts: Vec<_> = type_counts.iter().collect();
+            type_counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            rows.extend(type_counts.into_iter().map(|(object_type, count)| (date.as_str(), object_type.as_str(), *count)));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn cumulative_counts_grow() {
+        let mut sut = FileDates::default();
+        let day = Duration::from_secs(24 * 3600);
+        for (days, types) in [(0, vec!["A", "B"]), (1, vec!["A", "A"]), (2, vec!["B"])] {
+            sut.start_file(SystemTime::UNIX_EPOCH + day * days);
+            for object_type in types {
+                sut.record(object_type);
+            }
+        }
+        let expected = vec![
+            ("1970-01-01", "A", 1),
+            ("1970-01-01", "B", 1),
+            ("1970-01-02", "A", 3),
+            ("1970-01-02", "B", 1),
+            ("1970-01-03", "A", 3),
+            ("1970-01-03", "B", 2),
+        ];
+        assert_eq!(expected, sut.cumulative().sorted());
+    }
+}
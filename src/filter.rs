@@ -0,0 +1,44 @@
+//! Pre-filter of the raw lines, applied before they are parsed, for `--contains`.
+
+use regex::Regex;
+
+#[derive(Debug)]
+pub struct LineFilter {
+    // A literal pattern compiles to a plain substring search, and the regex crate also handles the case insensitive
+    // matching of non-ASCII text without lowercasing every line.
+    pattern: Regex,
+}
+
+impl LineFilter {
+    pub fn new(substring: &str, ignore_case: bool) -> anyhow::Result<Self> {
+        let flags = if ignore_case { "(?i)" } else { "" };
+        let pattern = Regex::new(&format!("{flags}{}", regex::escape(substring)))?;
+        Ok(Self { pattern })
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        self.pattern.is_match(line)
+    }
+}
+
+// `Regex` isn't comparable, two filters are the same if they were built from the same pattern.
+impl PartialEq for LineFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_sensitivity() {
+        let sut = LineFilter::new("Timeout.", false).unwrap();
+        assert!(sut.matches(r#"{"type":"A","message":"Timeout."}"#));
+        assert!(!sut.matches(r#"{"type":"A","message":"TIMEOUT."}"#));
+        assert!(!sut.matches(r#"{"type":"A","message":"Timeouts"}"#));
+        let sut = LineFilter::new("Timeout.", true).unwrap();
+        assert!(sut.matches(r#"{"type":"A","message":"TIMEOUT."}"#));
+    }
+}
@@ -0,0 +1,111 @@
+//! Conditions that fail the run once the stats are known: severity thresholds, budgets and outliers.
+
+use crate::cli::Args;
+use crate::{budget, output, severity, LogStats};
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct GateFailure {
+    /// Which option failed, e.g. `severity_threshold`.
+    pub gate: &'static str,
+    pub message: String,
+    /// The counts behind the failure, for `--error-report json`.
+    pub details: serde_json::Value,
+}
+
+/// Every gate that failed, all at once rather than making the user fix them one run at a time.
+pub fn failures(stats: &LogStats, args: &Args) -> Vec<GateFailure> {
+    let mut failures = Vec::new();
+    for breach in severity::breaches(&args.severity_rules, &args.severity_threshold, &stats.count_map) {
+        let types: serde_json::Map<_, _> =
+            breach.types.iter().map(|(object_type, count)| (object_type.to_string(), (*count).into())).collect();
+        failures.push(GateFailure {
+            gate: "severity_threshold",
+            message: format!("Severity threshold exceeded: {breach}"),
+            details: serde_json::json!({
+                "severity": breach.severity,
+                "total": breach.total,
+                "max": breach.max,
+                "types": types,
+            }),
+        });
+    }
+    if args.fail_on_budget {
+        for check in budget::check(&args.budgets, &stats.count_map, stats.rate_window()) {
+            if check.exceeded() {
+                failures.push(GateFailure {
+                    gate: "budget",
+                    message: check.to_string(),
+                    details: serde_json::json!({
+                        "type": check.budget.object_type,
+                        "limit": check.budget.limit,
+                        "max_per_second": check.budget.max_per_second,
+                        "rate": check.rate,
+                    }),
+                });
+            }
+        }
+    }
+    if let (Some(outliers), true) = (output::outliers(stats, args), args.fail_on_outliers) {
+        for (object_type, object_stats) in stats.sorted_entries() {
+            if outliers.is_outlier(object_stats.count) {
+                failures.push(GateFailure {
+                    gate: "outlier",
+                    message: format!(
+                        "Outlier {object_type}: {} lines, the mean is {:.2} with a standard deviation of {:.2}",
+                        object_stats.count, outliers.mean, outliers.std_dev
+                    ),
+                    details: serde_json::json!({
+                        "type": object_type,
+                        "count": object_stats.count,
+                        "mean": outliers.mean,
+                        "std_dev": outliers.std_dev,
+                        "sigmas": outliers.sigmas,
+                    }),
+                });
+            }
+        }
+    }
+    failures
+}
+
+/// Single line json report of the failures, so scripts can parse it straight from stderr.
+pub fn json_report(failures: &[GateFailure]) -> anyhow::Result<String> {
+    let report = serde_json::json!({ "failures": failures });
+    Ok(serde_json::to_string(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::ObjectStats;
+
+    #[test]
+    fn threshold_failure_json_report() {
+        let mut count_map = HashMap::new();
+        count_map.insert("payment_failed".to_string(), ObjectStats { count: 2, bytes: 20, content_hash: 0 });
+        count_map.insert("login".to_string(), ObjectStats { count: 9, bytes: 90, content_hash: 0 });
+        let stats = LogStats { count_map, ..Default::default() };
+        let args = Args::parse_from([
+            "word-counter",
+            "--severity-rule",
+            "error:.*fail.*",
+            "--severity-threshold",
+            "error:0",
+            "--error-report",
+            "json",
+        ]);
+        let report = json_report(&failures(&stats, &args)).unwrap();
+        assert!(!report.contains('\n'));
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let failure = &report["failures"][0];
+        assert_eq!("severity_threshold", failure["gate"]);
+        assert_eq!(2, failure["details"]["total"]);
+        assert_eq!(serde_json::json!({"payment_failed": 2}), failure["details"]["types"]);
+        assert_eq!(1, report["failures"].as_array().unwrap().len());
+        assert!(failures(&stats, &Args::parse_from(["word-counter"])).is_empty());
+    }
+}
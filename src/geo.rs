@@ -0,0 +1,105 @@
+//! Country of IP addresses from a local MaxMind database, for `--geo-field`.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use maxminddb::{geoip2, Reader};
+
+pub struct GeoDb {
+    path: PathBuf,
+    reader: Reader<Vec<u8>>,
+}
+
+impl GeoDb {
+    /// Loads the whole database in memory, the country ones are only a few megabytes.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let reader = Reader::open_readfile(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader,
+        })
+    }
+
+    /// ISO 3166-1 code of the country of `ip`, `None` if it isn't an IP or isn't in the database.
+    pub fn country(&self, ip: &str) -> Option<String> {
+        let ip: IpAddr = ip.trim().parse().ok()?;
+        let record: geoip2::Country = self.reader.lookup(ip).ok()?.decode().ok()??;
+        record.country.iso_code.map(str::to_string)
+    }
+}
+
+// The reader implements neither, a database is identified by its path.
+impl fmt::Debug for GeoDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoDb").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for GeoDb {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest possible IPv4 database: a single node sending the lower half of the addresses (first bit 0) to a
+    /// `{"country": {"iso_code": <iso_code>}}` record, and with no data for the upper half.
+    fn tiny_country_db(path: &Path, iso_code: &str) {
+        const NODE_COUNT: u32 = 1;
+        fn string(bytes: &mut Vec<u8>, value: &str) {
+            bytes.push(0x40 | value.len() as u8);
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        let mut db = Vec::new();
+        // Search tree, 24 bit records. Data pointers are offset by the node count and the 16 bytes separator.
+        db.extend_from_slice(&(NODE_COUNT + 16).to_be_bytes()[1..]);
+        db.extend_from_slice(&NODE_COUNT.to_be_bytes()[1..]);
+        db.extend_from_slice(&[0; 16]);
+        // Data section, one map per level.
+        db.push(0xE1);
+        string(&mut db, "country");
+        db.push(0xE1);
+        string(&mut db, "iso_code");
+        string(&mut db, iso_code);
+        // Metadata.
+        db.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+        db.push(0xE9);
+        string(&mut db, "binary_format_major_version");
+        db.extend_from_slice(&[0xA1, 2]);
+        string(&mut db, "binary_format_minor_version");
+        db.push(0xA0);
+        string(&mut db, "build_epoch");
+        db.extend_from_slice(&[0x00, 0x02]);
+        string(&mut db, "database_type");
+        string(&mut db, "Test-Country");
+        string(&mut db, "description");
+        db.push(0xE0);
+        string(&mut db, "ip_version");
+        db.extend_from_slice(&[0xA1, 4]);
+        string(&mut db, "languages");
+        db.extend_from_slice(&[0x00, 0x04]);
+        string(&mut db, "node_count");
+        db.extend_from_slice(&[0xC1, NODE_COUNT as u8]);
+        string(&mut db, "record_size");
+        db.extend_from_slice(&[0xA1, 24]);
+        std::fs::write(path, db).unwrap();
+    }
+
+    #[test]
+    fn country_of_known_ip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("country.mmdb");
+        tiny_country_db(&path, "SE");
+        let sut = GeoDb::open(&path).unwrap();
+        assert_eq!(Some("SE".to_string()), sut.country("89.160.20.112"));
+        assert_eq!(None, sut.country("200.160.20.112"));
+        assert_eq!(None, sut.country("2001:db8::1"));
+        assert_eq!(None, sut.country("not an ip"));
+        assert!(GeoDb::open(&dir.path().join("missing.mmdb")).is_err());
+    }
+}
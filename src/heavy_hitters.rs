@@ -0,0 +1,101 @@
+//! Approximate top-K types with bounded memory, using the Space-Saving algorithm.
+//!
+//! Only `capacity` counters are kept. When a new type arrives and they are all taken, the type with the lowest count is
+//! evicted and the new one inherits its count as the possible overestimation (error) of its own count. Any type with a
+//! true count above `total / capacity` is guaranteed to be kept, and every estimate is at most `error` above the truth.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub count: usize,
+    /// Upper bound of how much `count` overestimates the true count.
+    pub error: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct HeavyHitters {
+    capacity: usize,
+    counters: HashMap<String, Estimate>,
+}
+
+impl HeavyHitters {
+    pub fn new(capacity: usize) -> anyhow::Result<Self> {
+        if capacity == 0 {
+            anyhow::bail!("--heavy-hitters requires at least one counter");
+        }
+        Ok(Self {
+            capacity,
+            counters: HashMap::with_capacity(capacity),
+        })
+    }
+
+    pub fn record(&mut self, object_type: &str) {
+        if let Some(estimate) = self.counters.get_mut(object_type) {
+            estimate.count += 1;
+            return;
+        }
+        if self.counters.len() < self.capacity {
+            self.counters.insert(object_type.to_string(), Estimate { count: 1, error: 0 });
+            return;
+        }
+        // A linear scan is fine for the small K this mode is meant for, and avoids keeping a second index in sync.
+        let (evicted, min_count) = self
+            .counters
+            .iter()
+            .min_by(|a, b| a.1.count.cmp(&b.1.count).then_with(|| b.0.cmp(a.0)))
+            .map(|(object_type, estimate)| (object_type.clone(), estimate.count))
+            .unwrap();
+        self.counters.remove(&evicted);
+        self.counters.insert(object_type.to_string(), Estimate { count: min_count + 1, error: min_count });
+    }
+
+    /// Kept types sorted by descending estimated count.
+    pub fn top(&self) -> Vec<(&str, Estimate)> {
+        let mut top: Vec<_> = self
+            .counters
+            .iter()
+            .map(|(object_type, estimate)| (object_type.as_str(), *estimate))
+            .collect();
+        top.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavy_hitter_survives_long_tail() {
+        let mut sut = HeavyHitters::new(5).unwrap();
+        // Every other line is the heavy hitter, the rest are all distinct.
+        for index in 0..10_000 {
+            if index % 2 == 0 {
+                sut.record("heavy");
+            } else {
+                sut.record(&format!("tail-{index}"));
+            }
+        }
+        let top = sut.top();
+        assert_eq!(5, top.len());
+        let (object_type, estimate) = top[0];
+        assert_eq!("heavy", object_type);
+        assert!(estimate.count >= 5_000);
+        assert!(estimate.count - estimate.error <= 5_000);
+    }
+
+    #[test]
+    fn exact_while_under_capacity() {
+        let mut sut = HeavyHitters::new(3).unwrap();
+        for object_type in ["A", "B", "A", "C", "A"] {
+            sut.record(object_type);
+        }
+        let expected = vec![
+            ("A", Estimate { count: 3, error: 0 }),
+            ("B", Estimate { count: 1, error: 0 }),
+            ("C", Estimate { count: 1, error: 0 }),
+        ];
+        assert_eq!(expected, sut.top());
+    }
+}
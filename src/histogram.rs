@@ -0,0 +1,87 @@
+//! Counts of a numeric field bucketed into ranges, for `--histogram`.
+
+use std::fmt::Write;
+
+/// Bins are `[edge_i, edge_i+1)`, plus an underflow bucket for values below the first edge and an overflow one for the
+/// ones at or above the last.
+#[derive(Debug, PartialEq)]
+pub struct Histogram {
+    pub field: String,
+    pub edges: Vec<f64>,
+    /// `counts[0]` is the underflow, `counts[edges.len()]` the overflow and `counts[i]` the bin ending at `edges[i]`.
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(field: &str, edges: &[f64]) -> anyhow::Result<Self> {
+        if edges.is_empty() {
+            anyhow::bail!("--histogram requires at least one bin edge in --bins");
+        }
+        if edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+            anyhow::bail!("--bins edges must be strictly increasing");
+        }
+        Ok(Self {
+            field: field.to_string(),
+            edges: edges.to_vec(),
+            counts: vec![0; edges.len() + 1],
+        })
+    }
+
+    /// Counts the line in the bin of its field value. Lines without a numeric value are ignored.
+    pub fn record(&mut self, line: &serde_json::Value) {
+        if let Some(value) = line.get(&self.field).and_then(serde_json::Value::as_f64) {
+            // The number of edges lower or equal to the value is exactly the index of its bucket.
+            let bucket = self.edges.partition_point(|edge| *edge <= value);
+            self.counts[bucket] += 1;
+        }
+    }
+
+    /// Label and count of every bucket, in increasing order.
+    pub fn buckets(&self) -> Vec<(String, usize)> {
+        let last = self.edges.len();
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(bucket, count)| {
+                let mut label = String::new();
+                match bucket {
+                    0 => write!(label, "< {}", self.edges[0]),
+                    _ if bucket == last => write!(label, ">= {}", self.edges[last - 1]),
+                    _ => write!(label, "[{}, {})", self.edges[bucket - 1], self.edges[bucket]),
+                }
+                .unwrap();
+                (label, *count)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_land_in_their_bins() {
+        let mut sut = Histogram::new("latency_ms", &[0.0, 10.0, 50.0, 100.0, 500.0]).unwrap();
+        for latency in [-1.0, 0.0, 9.9, 10.0, 42.0, 99.0, 100.0, 499.0, 500.0, 1200.0] {
+            sut.record(&serde_json::json!({"latency_ms": latency}));
+        }
+        sut.record(&serde_json::json!({"latency_ms": "slow"}));
+        sut.record(&serde_json::json!({"other": 3}));
+        let expected = vec![
+            ("< 0".to_string(), 1),
+            ("[0, 10)".to_string(), 2),
+            ("[10, 50)".to_string(), 2),
+            ("[50, 100)".to_string(), 1),
+            ("[100, 500)".to_string(), 2),
+            (">= 500".to_string(), 2),
+        ];
+        assert_eq!(expected, sut.buckets());
+    }
+
+    #[test]
+    fn edges_must_increase() {
+        assert!(Histogram::new("latency_ms", &[10.0, 0.0]).is_err());
+        assert!(Histogram::new("latency_ms", &[]).is_err());
+    }
+}
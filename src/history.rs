@@ -0,0 +1,151 @@
+//! Run history kept as a json-lines file, one record per run.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::LogStats;
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryRecord {
+    /// RFC 3339 timestamp of the end of the run.
+    pub timestamp: String,
+    pub inputs: Vec<String>,
+    pub lines: usize,
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl HistoryRecord {
+    pub fn new(stats: &LogStats, inputs: &[String]) -> Self {
+        let counts: BTreeMap<_, _> = stats
+            .count_map
+            .iter()
+            .map(|(object_type, object_stats)| (object_type.clone(), object_stats.count))
+            .collect();
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            inputs: inputs.to_vec(),
+            lines: counts.values().sum(),
+            counts,
+        }
+    }
+}
+
+/// Appends the record as a single line. The file is locked while writing, so concurrent runs don't interleave records.
+pub fn append(path: &Path, record: &HistoryRecord) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(record).context("Failed to serialize history record")?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history file {}", path.display()))?;
+    file.lock().context("Failed to lock history file")?;
+    file.write_all(line.as_bytes()).context("Failed to write history record")?;
+    // The lock is released when the file is closed.
+    Ok(())
+}
+
+/// Changes within this percentage of the baseline are considered steady.
+const STEADY_PERCENT: f64 = 5.0;
+
+/// Average count of each type over the last runs of the history, the baseline the current counts are compared against.
+#[derive(Debug, Default, PartialEq)]
+pub struct Trend {
+    averages: HashMap<String, f64>,
+}
+
+impl Trend {
+    /// Averages the last `runs` records of the history. A missing history is an empty baseline, as in the first run.
+    pub fn load(path: &Path, runs: usize) -> anyhow::Result<Self> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("Failed to open history file {}", path.display())),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.context("Failed to read history file")?;
+            let record: HistoryRecord = serde_json::from_str(&line).context("Failed to parse history record")?;
+            records.push(record);
+        }
+        Ok(Self::new(&records[records.len().saturating_sub(runs)..]))
+    }
+
+    /// Types missing from some of the records count as zero in those runs.
+    fn new(records: &[HistoryRecord]) -> Self {
+        let mut averages = HashMap::new();
+        for record in records {
+            for (object_type, count) in &record.counts {
+                *averages.entry(object_type.clone()).or_default() += *count as f64 / records.len() as f64;
+            }
+        }
+        Self { averages }
+    }
+
+    /// Percentage change of the count against the baseline, `None` if the type isn't in any run of the baseline.
+    pub fn change(&self, object_type: &str, count: usize) -> Option<f64> {
+        let average = self.averages.get(object_type).filter(|average| **average > 0.0)?;
+        Some((count as f64 - average) / average * 100.0)
+    }
+
+    /// Arrow and percentage change, or `new` if the type isn't in the baseline.
+    pub fn label(&self, object_type: &str, count: usize) -> String {
+        match self.change(object_type, count) {
+            Some(change) if change >= STEADY_PERCENT => format!("↑ {change:+.1}%"),
+            Some(change) if change <= -STEADY_PERCENT => format!("↓ {change:+.1}%"),
+            Some(change) => format!("→ {change:+.1}%"),
+            None => "new".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ObjectStats;
+
+    #[test]
+    fn each_run_appends_a_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        let stats = LogStats { count_map, ..Default::default() };
+        let inputs = vec!["small.log".to_string()];
+        append(&path, &HistoryRecord::new(&stats, &inputs)).unwrap();
+        append(&path, &HistoryRecord::new(&stats, &inputs)).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<HistoryRecord> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(2, records.len());
+        assert_eq!(3, records[1].counts["A"]);
+        assert_eq!(inputs, records[1].inputs);
+    }
+
+    #[test]
+    fn trend_against_last_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        // The first run is out of the 3 runs window, the other ones average 10 `A` and 1 `B`.
+        let runs = [vec![("A", 1000), ("B", 1)], vec![("A", 8), ("B", 3)], vec![("A", 10)], vec![("A", 12)]];
+        for counts in runs {
+            let record = HistoryRecord {
+                timestamp: String::new(),
+                inputs: Vec::new(),
+                lines: 0,
+                counts: counts.iter().map(|(object_type, count)| (object_type.to_string(), *count)).collect(),
+            };
+            append(&path, &record).unwrap();
+        }
+        let sut = Trend::load(&path, 3).unwrap();
+        assert_eq!("↑ +50.0%", sut.label("A", 15));
+        assert_eq!("→ +0.0%", sut.label("A", 10));
+        assert_eq!("↓ -100.0%", sut.label("B", 0));
+        assert_eq!("new", sut.label("D", 1));
+        assert_eq!(Trend::default(), Trend::load(&dir.path().join("missing.jsonl"), 3).unwrap());
+    }
+}
@@ -0,0 +1,100 @@
+//! Publishing of the stats to a Kafka topic, one message per type.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext};
+use rdkafka::ClientContext;
+
+use crate::LogStats;
+
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Deliveries are acknowledged asynchronously, so the failures are collected here and checked after flushing.
+#[derive(Default)]
+struct DeliveryTracker {
+    failures: Mutex<Vec<String>>,
+}
+
+impl ClientContext for DeliveryTracker {}
+
+impl ProducerContext for DeliveryTracker {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, _delivery_opaque: Self::DeliveryOpaque) {
+        if let Err((error, _)) = delivery_result {
+            self.failures.lock().unwrap().push(error.to_string());
+        }
+    }
+}
+
+/// Produces a json message per type, keyed by the type, and waits until all of them are delivered.
+pub fn publish(stats: &LogStats, brokers: &str, topic: &str) -> anyhow::Result<usize> {
+    let producer: BaseProducer<DeliveryTracker> = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .create_with_context(DeliveryTracker::default())
+        .context("Failed to create Kafka producer")?;
+    let entries = stats.sorted_entries();
+    for (object_type, object_stats) in &entries {
+        let payload = serde_json::json!({"type": object_type, "count": object_stats.count, "bytes": object_stats.bytes});
+        let payload = serde_json::to_string(&payload).context("Failed to serialize Kafka message")?;
+        producer
+            .send(BaseRecord::to(topic).key(object_type.as_str()).payload(&payload))
+            .map_err(|(error, _)| error)
+            .with_context(|| format!("Failed to produce message for type {object_type}"))?;
+        // Serves the delivery callbacks of the messages sent so far.
+        producer.poll(Duration::ZERO);
+    }
+    producer.flush(FLUSH_TIMEOUT).context("Failed to flush Kafka messages")?;
+    let failures = producer.context().failures.lock().unwrap();
+    if !failures.is_empty() {
+        anyhow::bail!("Failed to deliver {} Kafka messages: {}", failures.len(), failures.join(", "));
+    }
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Instant;
+
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::message::Message;
+    use rdkafka::mocking::MockCluster;
+
+    use super::*;
+    use crate::ObjectStats;
+
+    #[test]
+    fn one_message_per_type() {
+        const TOPIC: &str = "word-counter";
+        let mock_cluster = MockCluster::new(1).unwrap();
+        mock_cluster.create_topic(TOPIC, 1, 1).unwrap();
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+        let stats = LogStats { count_map, ..Default::default() };
+        assert_eq!(2, publish(&stats, &mock_cluster.bootstrap_servers(), TOPIC).unwrap());
+
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", mock_cluster.bootstrap_servers())
+            .set("group.id", "word-counter-test")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap();
+        consumer.subscribe(&[TOPIC]).unwrap();
+        let mut messages = HashMap::new();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while messages.len() < 2 && Instant::now() < deadline {
+            if let Some(Ok(message)) = consumer.poll(Duration::from_millis(100)) {
+                let key = String::from_utf8(message.key().unwrap().to_vec()).unwrap();
+                let payload: serde_json::Value = serde_json::from_slice(message.payload().unwrap()).unwrap();
+                messages.insert(key, payload);
+            }
+        }
+        assert_eq!(3, messages["A"]["count"]);
+        assert_eq!(169, messages["B"]["bytes"]);
+    }
+}
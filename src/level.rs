@@ -0,0 +1,42 @@
+//! Log levels, counted with `--levels` and sorted from the least to the most severe rather than alphabetically.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// Levels from the least to the most severe.
+const LEVELS: &[&str] = &["TRACE", "DEBUG", "INFO", "WARN", "ERROR", "FATAL"];
+/// Other common spellings of the levels.
+const ALIASES: &[(&str, &str)] = &[("WARNING", "WARN"), ("ERR", "ERROR"), ("CRITICAL", "FATAL")];
+
+/// Upper cases the known levels and their aliases so `info` and `INFO` are counted together. Unknown levels are kept as
+/// they are.
+pub fn normalize(level: Cow<str>) -> Cow<str> {
+    let upper = level.to_ascii_uppercase();
+    if let Some((_, canonical)) = ALIASES.iter().find(|(alias, _)| *alias == upper) {
+        return Cow::Borrowed(canonical);
+    }
+    match LEVELS.iter().find(|known| **known == upper) {
+        Some(known) if *known != level => Cow::Borrowed(known),
+        _ => level,
+    }
+}
+
+/// Known levels by severity, then the unknown ones.
+pub fn cmp(a: &str, b: &str) -> Ordering {
+    let rank = |level| LEVELS.iter().position(|known| *known == level).unwrap_or(LEVELS.len());
+    rank(a).cmp(&rank(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_are_merged() {
+        assert_eq!("WARN", normalize(Cow::Borrowed("warning")));
+        assert_eq!("INFO", normalize(Cow::Borrowed("Info")));
+        assert_eq!("notice", normalize(Cow::Borrowed("notice")));
+        assert_eq!(Ordering::Less, cmp("DEBUG", "ERROR"));
+        assert_eq!(Ordering::Less, cmp("FATAL", "notice"));
+    }
+}
@@ -1,28 +1,391 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::time::Instant;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
-use cli_table::Style;
+use clap::Parser;
+use flate2::read::GzDecoder;
+
+use crate::background::BackgroundReader;
+use crate::bool_split::BoolSplit;
+use crate::budget::TimeSpan;
+use crate::checkpoint::Checkpoint;
+use crate::cli::{Args, Command, ErrorReport, OutputFormat};
+use crate::extremes::Extremes;
+use crate::file_dates::FileDates;
+use crate::filter::LineFilter;
+use crate::geo::GeoDb;
+use crate::heavy_hitters::HeavyHitters;
+use crate::histogram::Histogram;
+use crate::history::{HistoryRecord, Trend};
+use crate::memory::MemoryGuard;
+use crate::order::OrderCheck;
+use crate::progress::{CountingReader, Progress, TitleProgress};
+use crate::reverse::{ReadLine, ReverseLines};
+use crate::sample::Sampler;
+use crate::schema::SchemaChanges;
+use crate::transitions::Transitions;
+use crate::unique::UniqueLines;
+use crate::utf8::Utf8Check;
+
+mod access_log;
+mod atomic;
+mod background;
+mod baseline;
+mod bool_split;
+mod budget;
+mod cache;
+mod checkpoint;
+mod clipboard;
+mod cli;
+mod color;
+mod extremes;
+mod file_dates;
+mod filter;
+mod gates;
+mod geo;
+mod heavy_hitters;
+mod histogram;
+mod history;
+mod level;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod memory;
+mod metrics;
+mod normalize;
+mod order;
+mod output;
+mod progress;
+mod reverse;
+mod sample;
+mod schema;
+mod severity;
+mod snapshot;
+mod token;
+mod transitions;
+mod unique;
+mod utf8;
+mod walk;
 
 const CONFIG_FILE: &str = "config.toml";
+const STDIN_INPUT: &str = "-";
+/// Type the new types are counted as once `--auto-collapse-after` distinct types are counted.
+const OTHER_TYPE: &str = "(other)";
 
-fn main() -> anyhow::Result<()> {
-    let config = Config::new(CONFIG_FILE).context("Error loading config")?;
-    tracing_subscriber::fmt::init();
-    process_file(config.input_file)?;
-    Ok(())
+fn main() -> anyhow::Result<ExitCode> {
+    if clipboard::hold_if_holder()? {
+        return Ok(ExitCode::SUCCESS);
+    }
+    let args = Args::parse();
+    if args.print_default_config {
+        print!("{}", Config::default_template()?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.command == Some(Command::SelfTest) {
+        return Ok(if self_test()? { ExitCode::SUCCESS } else { ExitCode::FAILURE });
+    }
+    let config = Config::new(&args.config).context("Error loading config")?;
+    let mut inputs = args.inputs(&config.input_file);
+    if args.recursive {
+        inputs = walk::expand(inputs, &args.ext)?;
+    }
+    // Stdin can only be consumed once.
+    if args.config == STDIN_INPUT && inputs.iter().any(|input| input == STDIN_INPUT) {
+        anyhow::bail!("The config and the input can't both be read from stdin");
+    }
+    // Logs go to stderr so machine readable formats can be piped straight from stdout.
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+    let mut stats = match &args.load_snapshot {
+        Some(blob) => LogStats {
+            count_map: snapshot::decode(blob)?,
+            ..Default::default()
+        },
+        None => process_cached(&inputs, &args)?,
+    };
+    stats.log_performance();
+    if let Some(baseline) = &args.new_since {
+        baseline::retain_new(baseline, &mut stats.count_map)?;
+    }
+    // Loaded before this run is appended to the history, so it isn't part of its own baseline.
+    if let (Some(history), Some(runs)) = (&args.history, args.trend) {
+        stats.trend = Some(Trend::load(history, runs.get())?);
+    }
+    if args.spans {
+        stats.emit_spans();
+    }
+    let rendered = output::render(&stats, &args)?;
+    match &args.output {
+        Some(path) => atomic::write(path, rendered.as_bytes())?,
+        None => print!("{rendered}"),
+    }
+    if args.clipboard {
+        clipboard::copy(&rendered)?;
+    }
+    if let Some(history) = &args.history {
+        history::append(history, &HistoryRecord::new(&stats, &inputs))?;
+    }
+    #[cfg(feature = "kafka")]
+    if let (Some(brokers), Some(topic)) = (&args.kafka_brokers, &args.kafka_topic) {
+        let published = kafka::publish(&stats, brokers, topic)?;
+        tracing::info!("[kafka_topic={topic}][messages={published}]");
+    }
+    // The reports would break machine readable formats, so they only share stdout with the table.
+    let reports = output::reports(&stats, &args)?;
+    match args.format {
+        OutputFormat::Table => print!("{reports}"),
+        _ => eprint!("{reports}"),
+    }
+    let failures = gates::failures(&stats, &args);
+    if failures.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    match args.error_report {
+        Some(ErrorReport::Json) => eprintln!("{}", gates::json_report(&failures)?),
+        None => {
+            let messages: Vec<_> = failures.iter().map(|failure| failure.message.as_str()).collect();
+            eprintln!("Error: {}", messages.join("\n"));
+        }
+    }
+    Ok(ExitCode::FAILURE)
+}
+
+/// Counts a generated log through the same path as the inputs, prints its table and compares the counts with the
+/// expected ones. The default options are used, the subcommand can't be given any.
+fn self_test() -> anyhow::Result<bool> {
+    let mut content = String::new();
+    for index in 0..100 {
+        content.push_str(&format!("{{\"type\":\"A\",\"index\":{index}}}\n"));
+        if index % 4 == 0 {
+            content.push_str(&format!("{{\"index\":{index},\"type\":\"B\",\"items\":[\"one\",\"two\"]}}\n"));
+        }
+        if index % 10 == 0 {
+            content.push_str("not json\n");
+        }
+    }
+    content.push_str("{\"type\": \"C\"}");
+    // Sorted by type so the expected and actual counts can be compared at a glance if they differ.
+    let expected = BTreeMap::from([("A", 100), ("B", 25), ("C", 1)]);
+
+    let args = Args::parse_from(["word-counter"]);
+    let mut stats = LogStats::new(&args)?;
+    process_reader(content.as_bytes(), &mut stats, &args, &mut InputTracking::default())?;
+    print!("{}", output::render(&stats, &args)?);
+    let counts: BTreeMap<_, _> = stats.count_map.iter().map(|(key, stats)| (key.as_str(), stats.count)).collect();
+    if counts == expected {
+        println!("Self-test PASS");
+        return Ok(true);
+    }
+    println!("Self-test FAIL: expected {expected:?}, got {counts:?}");
+    Ok(false)
+}
+
+/// Loads the stats of a previous run over the same unchanged inputs if there is one in the `--cache-dir`, otherwise
+/// processes the inputs and caches the result.
+fn process_cached(inputs: &[String], args: &Args) -> anyhow::Result<LogStats> {
+    let entry_path = match &args.cache_dir {
+        Some(cache_dir) if !args.no_cache => cache::entry_path(cache_dir, inputs, args)?,
+        _ => None,
+    };
+    let Some(entry_path) = entry_path else {
+        return process_inputs(inputs, args);
+    };
+    if let Some(stats) = cache::load(&entry_path, args) {
+        tracing::debug!("[cache_hit={}]", entry_path.display());
+        return Ok(stats);
+    }
+    let stats = process_inputs(inputs, args)?;
+    cache::store(&entry_path, &stats)?;
+    Ok(stats)
+}
+
+// Every input is accumulated into the same `LogStats`, so the output is the combined count of all of them.
+fn process_inputs(inputs: &[String], args: &Args) -> anyhow::Result<LogStats> {
+    let mut stats = LogStats::new(args)?;
+    // An offset only makes sense within a single file.
+    if args.resume_from.is_some() && (inputs.len() != 1 || inputs[0] == STDIN_INPUT) {
+        anyhow::bail!("--resume-from requires a single input file");
+    }
+    // Every file would overwrite the offset of the previous one.
+    if args.checkpoint.is_some() && (inputs.len() != 1 || inputs[0] == STDIN_INPUT) {
+        anyhow::bail!("--checkpoint requires a single input file");
+    }
+    if args.reverse_read && inputs.iter().any(|input| input == STDIN_INPUT) {
+        anyhow::bail!("--reverse-read can't read from stdin");
+    }
+    for input in inputs {
+        if args.head.is_some_and(|head| stats.lines_read >= head) {
+            break;
+        }
+        if let (Some(file_dates), true) = (&mut stats.file_dates, input == STDIN_INPUT) {
+            file_dates.start_stdin();
+        }
+        if let Some(transitions) = &mut stats.transitions {
+            transitions.start_input();
+        }
+        if input == STDIN_INPUT && args.root_path.is_some() {
+            process_document(std::io::stdin().lock(), 0, &mut stats, args)?;
+        } else if input == STDIN_INPUT {
+            let mut stdin = std::io::stdin().lock();
+            let offset = checkpoint::skip_to_line(&mut stdin, args.skip_header_bytes)?;
+            process_buffered(stdin, offset, &mut stats, args, &mut InputTracking::default())?;
+        } else {
+            let initial_len = match args.warn_if_growing {
+                true => Some(std::fs::metadata(input).with_context(|| format!("Failed to read {input} metadata"))?.len()),
+                false => None,
+            };
+            process_file(input, &mut stats, args).with_context(|| format!("Failed to process {input}"))?;
+            if let Some(initial_len) = initial_len {
+                warn_if_grown(input, initial_len)?;
+            }
+        }
+    }
+    if let Some(rate) = args.sample_rate {
+        sample::scale(&mut stats.count_map, rate);
+    }
+    if let Some(schema_changes) = &mut stats.schema_changes {
+        schema_changes.finish();
+    }
+    stats.elapsed = stats.start.elapsed();
+    Ok(stats)
 }
 
-fn process_file<P: AsRef<Path> + Clone>(path: P) -> anyhow::Result<LogStats> {
+/// Warns if the file is bigger than when it started being processed, as the counts would then be a snapshot of a file
+/// that is still being written. Returns whether it did.
+fn warn_if_grown(path: &str, initial_len: u64) -> anyhow::Result<bool> {
+    let len = std::fs::metadata(path).with_context(|| format!("Failed to read {path} metadata"))?.len();
+    if len <= initial_len {
+        return Ok(false);
+    }
+    tracing::warn!(
+        "{path} grew by {} bytes while it was processed, it's probably still being written and the lines appended \
+         meanwhile may not be counted",
+        len - initial_len
+    );
+    Ok(true)
+}
+
+fn process_file<P: AsRef<Path>>(path: P, stats: &mut LogStats, args: &Args) -> anyhow::Result<()> {
     // First step is opening the file and creating a reader.
+    let gzip = path.as_ref().extension().is_some_and(|extension| extension == "gz");
     let file = File::open(path).context("Failed to open file")?;
 
-    // While we are here, we also get the file size and create the instance of `LogStats`.
-    let file_len_bytes = file.metadata().expect("Failed to read file metadata").len();
-    let mut stats = LogStats::new(file_len_bytes);
+    // While we are here, we also get the file size to report the throughput.
+    let metadata = file.metadata().expect("Failed to read file metadata");
+    let file_len_bytes = metadata.len();
+    stats.file_len_bytes += file_len_bytes;
+    if let Some(file_dates) = &mut stats.file_dates {
+        file_dates.start_file(metadata.modified().context("Failed to read file modification date")?);
+    }
+    if args.root_path.is_some() && gzip {
+        return process_document(GzDecoder::new(file), file_len_bytes, stats, args);
+    } else if args.root_path.is_some() {
+        return process_document(file, file_len_bytes, stats, args);
+    }
+    let mut tracking = InputTracking {
+        progress: args.progress.then(|| Progress::new(file_len_bytes)),
+        ..Default::default()
+    };
+    // The progress is measured on the bytes read from the file itself, which for compressed inputs are the compressed
+    // bytes. That's the only size we know before decompressing the whole file.
+    let counter = tracking.progress.as_ref().map(Progress::counter).unwrap_or_default();
+    if args.reverse_read {
+        if gzip {
+            anyhow::bail!("--reverse-read isn't supported for gzip inputs");
+        }
+        let start = checkpoint::seek_to_line(&mut BufReader::new(&file), args.skip_header_bytes)?;
+        process_reader(ReverseLines::new(file, start).context("Failed to seek input")?, stats, args, &mut tracking)?;
+        return tracking.finish();
+    }
+    let file = CountingReader::new(file, counter);
+    if gzip {
+        if args.resume_from.is_some() || args.checkpoint.is_some() {
+            anyhow::bail!("--resume-from and --checkpoint aren't supported for gzip inputs");
+        }
+        // The header is skipped after decompressing, the size of a compressed header isn't known.
+        if args.parallel_gzip {
+            let mut reader = BufReader::new(BackgroundReader::spawn(GzDecoder::new(file)));
+            let offset = checkpoint::skip_to_line(&mut reader, args.skip_header_bytes)?;
+            process_buffered(reader, offset, stats, args, &mut tracking)?;
+        } else {
+            let mut reader = BufReader::new(GzDecoder::new(file));
+            let offset = checkpoint::skip_to_line(&mut reader, args.skip_header_bytes)?;
+            process_buffered(reader, offset, stats, args, &mut tracking)?;
+        }
+    } else {
+        let mut reader = BufReader::new(file);
+        // Offsets to resume from are offsets of the whole file, so they may already be past the header.
+        let start = args.resume_from.unwrap_or_default().max(args.skip_header_bytes);
+        let offset = checkpoint::seek_to_line(&mut reader, start)?;
+        tracking.checkpoint = args.checkpoint.as_ref().map(|path| Checkpoint::new(path, offset));
+        process_buffered(reader, offset, stats, args, &mut tracking)?;
+    }
+    tracking.finish()
+}
+
+/// Bookkeeping of the input being read that isn't part of the stats.
+#[derive(Default)]
+struct InputTracking {
+    checkpoint: Option<Checkpoint>,
+    progress: Option<Progress>,
+}
+
+impl InputTracking {
+    fn line_read(&mut self, num_bytes: usize) -> anyhow::Result<()> {
+        if let Some(checkpoint) = &mut self.checkpoint {
+            checkpoint.advance(num_bytes)?;
+        }
+        if let Some(progress) = &mut self.progress {
+            progress.tick();
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> anyhow::Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+        match &self.checkpoint {
+            Some(checkpoint) => checkpoint.write(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads the lines with `process_reader`, validating them with `--strict-utf8`. `offset` is where the reader is in the
+/// input, for the reported offsets of the invalid bytes.
+fn process_buffered<R: BufRead>(
+    reader: R,
+    offset: u64,
+    stats: &mut LogStats,
+    args: &Args,
+    tracking: &mut InputTracking,
+) -> anyhow::Result<()> {
+    match args.strict_utf8 {
+        true => process_reader(Utf8Check::new(reader, offset, args.continue_on_invalid_utf8), stats, args, tracking),
+        false => process_reader(reader, stats, args, tracking),
+    }
+}
+
+fn process_reader<R: ReadLine>(
+    mut reader: R,
+    stats: &mut LogStats,
+    args: &Args,
+    tracking: &mut InputTracking,
+) -> anyhow::Result<()> {
+    // The guard samples the RSS while we stream the input, which should only grow with the number of distinct types.
+    let memory_guard = args
+        .assert_constant_memory
+        .then(|| MemoryGuard::new(args.memory_bound_mb))
+        .transpose()?;
+    let mut title_progress = progress::title_progress_enabled(args.title_progress, args.force_tty)
+        .then(|| TitleProgress::new(std::io::stderr()));
 
     // Options to iterate the lines using the `BufReader`:
     //  - `lines()`: iterates each line allocating a new `String` each time. The string doesn't contain `\n`.
@@ -31,45 +394,182 @@ fn process_file<P: AsRef<Path> + Clone>(path: P) -> anyhow::Result<LogStats> {
     // The obvious problem with this approach is memory consumption as you have to read the whole file and store it in memory.
     // It would be probably better to split the input file in smaller files, processing them concurrently, and accumulate
     // the results as a final step (mapreduce approach).
-    let mut reader = BufReader::new(file);
     let mut buffer = String::new();
+    let mut lines_read: usize = 0;
     loop {
+        if args.head.is_some_and(|head| stats.lines_read >= head) {
+            break;
+        }
         let num_bytes = reader.read_line(&mut buffer).context("Failed to read line")?;
 
         // If num_bytes is 0, the current line is empty, so we assume this is the EOF.
         if num_bytes == 0 {
             break;
         }
-
-        // Now we need to process the readline. The first thing we have to do is deserializing the line into a `LogLine` instance.
-        // This step doesn't allocate new memory, since `LogLine`'s only holds a reference to the `str` from the `String` buffer.
-        if let Ok(log_line) = serde_json::from_str::<LogLine>(&buffer) {
-            // If the key exists in the hashmap, we get a mutable reference to its associated value.
-            match stats.count_map.get_mut(log_line.object_type) {
-                // If the key is in the hashmap, we just increase the counters. No allocations needed.
-                Some(object_stats) => {
-                    object_stats.count += 1;
-                    object_stats.bytes += num_bytes;
-                }
-                // If the key is not in the hashmap, we add a new entry initializing a new instance of `ObjectStats`.
-                // In this case, we need to own the `str` to use it later on, as the values it's pointing at will be erased
-                // after the current iteration ends. In other words, we need an to perform an extra `String` allocation
-                // everytime we need to add a new key so the hashmap can save the value of the current `type` value and
-                // use it outside this iteration to build and output the stats table.
-                None => {
-                    stats.count_map
-                        .insert(log_line.object_type.to_string(), ObjectStats::new(num_bytes));
-                }
+        lines_read += 1;
+        stats.lines_read += 1;
+        tracking.line_read(num_bytes)?;
+        if let Some(title_progress) = &mut title_progress {
+            title_progress.tick(stats.lines_read);
+        }
+        if let Some(memory_guard) = &memory_guard {
+            if lines_read.is_multiple_of(memory::SAMPLE_INTERVAL_LINES) {
+                memory_guard.check(stats.count_map.len())?;
             }
-        } else {
-            // The current line couldn't be deserialized into a `LogLine` instance, so we do nothing with it.
         }
 
+        process_line(&buffer, num_bytes, stats, args);
+
         // Clear the buffer to avoid accumulating data.
         buffer.clear();
     }
-    stats.print()?;
-    Ok(stats)
+    if let Some(title_progress) = &mut title_progress {
+        title_progress.finish(stats.lines_read);
+    }
+    if let Some(memory_guard) = &memory_guard {
+        memory_guard.check(stats.count_map.len())?;
+    }
+    Ok(())
+}
+
+fn process_line(line: &str, num_bytes: usize, stats: &mut LogStats, args: &Args) {
+    // Lines left out of the sample are ignored like the filtered out ones, the counts are scaled up at the end.
+    if stats.sampler.as_mut().is_some_and(|sampler| !sampler.keep()) {
+        return;
+    }
+    // Filtered out lines are left out entirely, they don't count as skipped either.
+    if stats.line_filter.as_ref().is_some_and(|line_filter| !line_filter.matches(line)) {
+        return;
+    }
+    if let Some(preset) = args.format_preset {
+        match access_log::parse(preset, line) {
+            Some(value) => process_value(&value, line, num_bytes, stats, args),
+            None => stats.skip_line(),
+        }
+        return;
+    }
+
+    // Some features need fields other than `type`, in which case the line is deserialized once into a generic json value
+    // that all of them share. This allocates, so we only do it when one of those features is enabled.
+    if args.needs_json_value() {
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(value) => process_value(&value, line, num_bytes, stats, args),
+            Err(_) => stats.skip_line(),
+        }
+        return;
+    }
+
+    // Now we need to process the readline. The first thing we have to do is deserializing the line into a `LogLine` instance.
+    // This step doesn't allocate new memory, since `LogLine`'s only holds a reference to the `str` from the `String` buffer.
+    if let Ok(log_line) = serde_json::from_str::<LogLine>(line) {
+        // Normalizing the value only allocates when it actually changes it, otherwise we keep borrowing the buffer.
+        let object_type = normalize::object_type(log_line.object_type, args);
+        stats.record(&object_type, line, num_bytes);
+    } else {
+        // The current line couldn't be deserialized into a `LogLine` instance, so it isn't counted.
+        stats.skip_line();
+    }
+}
+
+fn process_value(value: &serde_json::Value, line: &str, num_bytes: usize, stats: &mut LogStats, args: &Args) {
+    if let Some(histogram) = &mut stats.histogram {
+        histogram.record(value);
+    }
+    if let Some(order_check) = &mut stats.order_check {
+        order_check.record(value);
+    }
+    if let Some(time_span) = &mut stats.time_span {
+        time_span.record(value);
+    }
+    if let Some(schema_changes) = &mut stats.schema_changes {
+        schema_changes.record(stats.lines_read, value);
+    }
+    if let Some(object_type) = line_key(value, stats.geo_db.as_ref(), args) {
+        let object_type = normalize::object_type(&object_type, args);
+        // Collapsed first, so the split of the new types is the split of `OTHER_TYPE` and doesn't grow either.
+        let object_type = stats.collapsed_type(&object_type);
+        if let Some(bool_split) = &mut stats.bool_split {
+            bool_split.record(object_type, value);
+        }
+        stats.record_collapsed(object_type, line, num_bytes);
+    } else {
+        stats.skip_line();
+    }
+}
+
+/// Scalars can be used as a key, so fields like a numeric status code can be counted too.
+fn key_value(value: &serde_json::Value) -> Option<Cow<'_, str>> {
+    match value {
+        serde_json::Value::String(value) => Some(Cow::Borrowed(value)),
+        serde_json::Value::Number(value) => Some(Cow::Owned(value.to_string())),
+        serde_json::Value::Bool(value) => Some(Cow::Owned(value.to_string())),
+        _ => None,
+    }
+}
+
+/// Value the line is counted by, taken from the field given by whichever of the key options is set.
+fn line_key<'a>(value: &'a serde_json::Value, geo_db: Option<&GeoDb>, args: &Args) -> Option<Cow<'a, str>> {
+    if let (Some(field), Some(geo_db)) = (&args.geo_field, geo_db) {
+        let ip = value.get(field)?.as_str()?;
+        return geo_db.country(ip).map(Cow::Owned);
+    }
+    if let Some(token_field) = &args.token_field {
+        return token_field.extract(value).map(Cow::Borrowed);
+    }
+    if let Some(time_prefix) = &args.time_prefix {
+        return time_prefix.extract(value).map(Cow::Borrowed);
+    }
+    if let Some(field) = &args.levels {
+        return value.get(field).and_then(key_value).map(level::normalize);
+    }
+    if !args.key_join.is_empty() {
+        return joined_key(value, &args.key_join, &args.key_separator).map(Cow::Owned);
+    }
+    value.get(args.key_field()).and_then(key_value)
+}
+
+/// Values of all the `fields` joined with `separator`, `None` if any of them is missing.
+fn joined_key(value: &serde_json::Value, fields: &[String], separator: &str) -> Option<String> {
+    let values = fields
+        .iter()
+        .map(|field| value.get(field).and_then(key_value))
+        .collect::<Option<Vec<_>>>()?;
+    Some(values.join(separator))
+}
+
+/// Counts the elements of the array at `--root-path` of a single json document. Unlike the lines of a log, the whole
+/// document has to be held in memory to be parsed.
+fn process_document<R: Read>(mut reader: R, size_hint: u64, stats: &mut LogStats, args: &Args) -> anyhow::Result<()> {
+    let pointer = args.root_path.as_deref().unwrap_or_default();
+    let mut content = Vec::new();
+    content.try_reserve_exact(size_hint as usize).map_err(|_| {
+        anyhow::anyhow!("The document is {size_hint} bytes and doesn't fit in memory, --root-path needs to load it whole")
+    })?;
+    reader.read_to_end(&mut content).context("Failed to read document")?;
+    let document: serde_json::Value = serde_json::from_slice(&content).context("Failed to parse json document")?;
+    drop(content);
+    let items = document
+        .pointer(pointer)
+        .with_context(|| format!("Nothing found at {pointer}"))?
+        .as_array()
+        .with_context(|| format!("The value at {pointer} isn't an array"))?;
+    for item in items {
+        if args.head.is_some_and(|head| stats.lines_read >= head) {
+            break;
+        }
+        stats.lines_read += 1;
+        // The elements are sampled like the lines, so the scaled up counts are estimates of the whole array too.
+        if stats.sampler.as_mut().is_some_and(|sampler| !sampler.keep()) {
+            continue;
+        }
+        // There are no lines to measure, an element stands for its compact serialization.
+        let line = serde_json::to_string(item).unwrap_or_default();
+        if stats.line_filter.as_ref().is_some_and(|line_filter| !line_filter.matches(&line)) {
+            continue;
+        }
+        process_value(item, &line, line.len(), stats, args);
+    }
+    Ok(())
 }
 
 // The json object structure is dynamic and we are only interested in this field.
@@ -83,16 +583,75 @@ struct LogLine<'a> {
 #[derive(Debug, PartialEq)]
 pub struct LogStats {
     pub file_len_bytes: u64,
+    /// Lines read from all the inputs so far, counted or not.
+    pub lines_read: usize,
     pub start: Instant,
+    pub elapsed: Duration,
     pub count_map: HashMap<String, ObjectStats>,
+    pub histogram: Option<Histogram>,
+    pub order_check: Option<OrderCheck>,
+    pub time_span: Option<TimeSpan>,
+    pub transitions: Option<Transitions>,
+    pub file_dates: Option<FileDates>,
+    pub heavy_hitters: Option<HeavyHitters>,
+    pub line_filter: Option<LineFilter>,
+    pub geo_db: Option<GeoDb>,
+    pub bool_split: Option<BoolSplit>,
+    pub unique_lines: Option<UniqueLines>,
+    pub extremes: Option<Extremes>,
+    pub sampler: Option<Sampler>,
+    pub schema_changes: Option<SchemaChanges>,
+    /// Whether `ObjectStats::content_hash` is computed, it hashes every line so it's opt-in.
+    pub content_hash: bool,
+    /// Distinct types counted before the new ones are collapsed into `OTHER_TYPE`.
+    pub collapse_after: Option<usize>,
+    /// Whether the types are `--levels`, sorted by severity instead of by count.
+    pub level_order: bool,
+    /// Baseline of the `--trend` column, loaded from the history once the inputs are processed.
+    pub trend: Option<Trend>,
 }
 
 impl LogStats {
-    fn new(file_len_bytes: u64) -> Self {
-        Self {
-            file_len_bytes,
+    /// Creates the stats with the extra accumulators required by the enabled features.
+    fn new(args: &Args) -> anyhow::Result<Self> {
+        let histogram = match &args.histogram {
+            Some(field) => Some(Histogram::new(field, &args.bins)?),
+            None => None,
+        };
+        let order_check = match (&args.time_field, args.check_order) {
+            (Some(field), true) => Some(OrderCheck::new(field)),
+            _ => None,
+        };
+        // The budgets' rates are computed over the span of the logs when they are timestamped, see `rate_window`.
+        let time_span = match (&args.time_field, args.budgets.is_empty()) {
+            (Some(field), false) => Some(TimeSpan::new(field)),
+            _ => None,
+        };
+        let heavy_hitters = args.heavy_hitters.map(HeavyHitters::new).transpose()?;
+        let geo_db = args.geo_db.as_deref().map(GeoDb::open).transpose()?;
+        let line_filter = match &args.contains {
+            Some(substring) => Some(LineFilter::new(substring, args.ignore_case)?),
+            None => None,
+        };
+        Ok(Self {
+            histogram,
+            heavy_hitters,
+            line_filter,
+            geo_db,
+            bool_split: args.split_bool.as_deref().map(BoolSplit::new),
+            unique_lines: args.unique_lines.then(UniqueLines::default),
+            extremes: args.extremes.then(Extremes::default),
+            sampler: args.sample_rate.map(|rate| Sampler::new(rate, args.seed)),
+            schema_changes: args.detect_schema_change.then(|| SchemaChanges::new(args.schema_window.get())),
+            order_check,
+            time_span,
+            transitions: args.transitions.then(|| Transitions::new(args.reset_on_skip)),
+            file_dates: args.by_file_date.then(FileDates::default),
+            content_hash: args.content_hash,
+            collapse_after: args.auto_collapse_after.map(std::num::NonZeroUsize::get),
+            level_order: args.levels.is_some(),
             ..Default::default()
-        }
+        })
     }
 }
 
@@ -100,58 +659,170 @@ impl Default for LogStats {
     fn default() -> Self {
         Self {
             file_len_bytes: 0,
+            lines_read: 0,
             start: Instant::now(),
+            elapsed: Duration::ZERO,
             count_map: Default::default(),
+            histogram: None,
+            order_check: None,
+            time_span: None,
+            transitions: None,
+            file_dates: None,
+            heavy_hitters: None,
+            line_filter: None,
+            geo_db: None,
+            bool_split: None,
+            unique_lines: None,
+            extremes: None,
+            sampler: None,
+            schema_changes: None,
+            content_hash: false,
+            collapse_after: None,
+            level_order: false,
+            trend: None,
         }
     }
 }
 
 impl LogStats {
-    fn print(&mut self) -> anyhow::Result<()> {
-        // Performance stats
-        {
-            let time_elapsed = self.start.elapsed();
-            let file_size_mb = self.file_len_bytes / 1_048_576;
-            let throughput = file_size_mb as f64 / time_elapsed.as_secs_f64();
-            let lines_processed = self.count_map.iter().map(|x| x.1.count).sum::<usize>();
-            let unique_keys = self.count_map.keys().count();
-            tracing::info!("[time={time_elapsed:?}][file_size={file_size_mb}MB][throughput={throughput:.2}MB/s][lines={lines_processed:?}][unique_types={unique_keys}]");
-        }
-        // Table with keys and counts
-        {
-            use cli_table::{format::Justify, Cell, Table, print_stdout};
-            let mut rows = vec![];
-            for data in self.count_map.iter() {
-                rows.push(vec![
-                    data.0.cell().justify(Justify::Right),
-                    data.1.count.cell().justify(Justify::Right),
-                    data.1.bytes.cell().justify(Justify::Right),
-                ]);
+    fn log_performance(&self) {
+        let time_elapsed = self.elapsed;
+        let file_size_mb = self.file_len_bytes / 1_048_576;
+        let throughput = file_size_mb as f64 / time_elapsed.as_secs_f64();
+        let lines_processed = self.count_map.iter().map(|x| x.1.count).sum::<usize>();
+        let unique_keys = self.count_map.keys().count();
+        tracing::info!("[time={time_elapsed:?}][file_size={file_size_mb}MB][throughput={throughput:.2}MB/s][lines={lines_processed:?}][unique_types={unique_keys}]");
+    }
+
+    /// One span per type carrying its stats, so subscribers exporting spans (e.g. to OTLP) get them as attributes.
+    fn emit_spans(&self) {
+        for (object_type, object_stats) in self.sorted_entries() {
+            let span = tracing::info_span!(
+                "object_stats",
+                object_type = object_type.as_str(),
+                count = object_stats.count as u64,
+                bytes = object_stats.bytes as u64
+            );
+            let _entered = span.enter();
+            // Subscribers that only print events, like the default one, would otherwise show nothing.
+            tracing::info!("type processed");
+        }
+    }
+
+    fn record(&mut self, object_type: &str, line: &str, num_bytes: usize) {
+        let object_type = self.collapsed_type(object_type);
+        self.record_collapsed(object_type, line, num_bytes);
+    }
+
+    /// Type the line is counted as, `OTHER_TYPE` for the new types once `--auto-collapse-after` types are counted.
+    fn collapsed_type<'a>(&self, object_type: &'a str) -> &'a str {
+        match self.collapse_after {
+            Some(limit) if self.count_map.len() >= limit && !self.count_map.contains_key(object_type) => {
+                if !self.count_map.contains_key(OTHER_TYPE) {
+                    tracing::warn!("More than {limit} distinct types, the new ones are counted as {OTHER_TYPE}");
+                }
+                OTHER_TYPE
+            }
+            _ => object_type,
+        }
+    }
+
+    /// Counts a line whose type already went through `collapsed_type`.
+    fn record_collapsed(&mut self, object_type: &str, line: &str, num_bytes: usize) {
+        if let Some(transitions) = &mut self.transitions {
+            transitions.record(object_type);
+        }
+        if let Some(file_dates) = &mut self.file_dates {
+            file_dates.record(object_type);
+        }
+        if let Some(unique_lines) = &mut self.unique_lines {
+            unique_lines.record(object_type, line);
+        }
+        if let Some(extremes) = &mut self.extremes {
+            extremes.record(object_type, line, num_bytes);
+        }
+        // The point of the heavy hitters is keeping the memory bounded, so they replace the exact counts.
+        if let Some(heavy_hitters) = &mut self.heavy_hitters {
+            heavy_hitters.record(object_type);
+            return;
+        }
+        let line_hash = if self.content_hash { line_hash(line) } else { 0 };
+        // If the key exists in the hashmap, we get a mutable reference to its associated value.
+        match self.count_map.get_mut(object_type) {
+            // If the key is in the hashmap, we just increase the counters. No allocations needed.
+            Some(object_stats) => {
+                object_stats.count += 1;
+                object_stats.bytes += num_bytes;
+                object_stats.content_hash ^= line_hash;
+            }
+            // If the key is not in the hashmap, we add a new entry initializing a new instance of `ObjectStats`.
+            // In this case, we need to own the `str` to use it later on, as the values it's pointing at will be erased
+            // after the current iteration ends. In other words, we need an to perform an extra `String` allocation
+            // everytime we need to add a new key so the hashmap can save the value of the current `type` value and
+            // use it outside this iteration to build and output the stats table.
+            None => {
+                self.count_map
+                    .insert(object_type.to_string(), ObjectStats::new(num_bytes, line_hash));
             }
-            let table = rows
-                .table()
-                .title(vec!["Type".cell().bold(true), "Count".cell().bold(true), "Size Bytes".cell().bold(true)]);
-            print_stdout(table).context("Failed to print stats table")
         }
     }
+
+    /// Called for the lines that couldn't be counted.
+    fn skip_line(&mut self) {
+        if let Some(transitions) = &mut self.transitions {
+            transitions.skip();
+        }
+    }
+
+    /// Time the counts are spread over: the span of the timestamps with `--time-field`, the processing time otherwise.
+    fn rate_window(&self) -> Duration {
+        self.time_span.as_ref().map_or(self.elapsed, TimeSpan::duration)
+    }
+
+    /// Entries sorted by descending count, breaking ties by type so the output is stable across runs. Levels are sorted
+    /// by severity first.
+    fn sorted_entries(&self) -> Vec<(&String, &ObjectStats)> {
+        let mut entries: Vec<_> = self.count_map.iter().collect();
+        entries.sort_by(|a, b| {
+            let by_level = if self.level_order { level::cmp(a.0, b.0) } else { std::cmp::Ordering::Equal };
+            by_level.then_with(|| b.1.count.cmp(&a.1.count)).then_with(|| a.0.cmp(b.0))
+        });
+        entries
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ObjectStats {
     pub count: usize,
     pub bytes: usize,
+    /// XOR of the hashes of the lines of this type with `--content-hash`, so it doesn't depend on their order. Zero
+    /// otherwise. A line repeated an even number of times cancels itself out.
+    #[serde(skip)]
+    pub content_hash: u64,
 }
 
 impl ObjectStats {
-    fn new(bytes: usize) -> Self {
+    fn new(bytes: usize, content_hash: u64) -> Self {
         Self {
             count: 1,
             bytes,
+            content_hash,
         }
     }
+
+    /// Bytes per second over the processing time, `None` if no time elapsed at all (e.g. empty input).
+    fn byte_rate(&self, elapsed: Duration) -> Option<f64> {
+        let elapsed_secs = elapsed.as_secs_f64();
+        (elapsed_secs > 0.0).then(|| self.bytes as f64 / elapsed_secs)
+    }
+}
+
+// The line ending isn't part of the content, so the last line of a file hashes the same with or without it.
+fn line_hash(line: &str) -> u64 {
+    color::fnv1a(line.trim_end_matches(['\n', '\r']).as_bytes())
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 struct Config {
     log_level: String,
@@ -159,22 +830,57 @@ struct Config {
 }
 
 impl Config {
+    /// Loads the config from `path`, or from stdin if `path` is `-`.
     fn new(path: &str) -> anyhow::Result<Self> {
         use config::Config as CConfig;
         let mut c = CConfig::new();
         let config: Config = {
-            if std::path::Path::new(path).exists() {
+            if path == STDIN_INPUT {
+                Self::parse(std::io::stdin().lock())?
+            } else if std::path::Path::new(path).exists() {
                 c.merge(config::File::with_name(path))?;
                 c.try_into()?
-            } else {
+            } else if path == CONFIG_FILE {
+                // Only the implicit default config is optional, a config given with `--config` has to exist.
                 Config::default()
+            } else {
+                anyhow::bail!("Config file {path} not found");
             }
         };
         std::env::set_var("RUST_LOG", &config.log_level);
         Ok(config)
     }
+
+    /// Commented TOML with every key and its default value, generated from `Config::default()`.
+    fn default_template() -> anyhow::Result<String> {
+        let defaults = toml::Value::try_from(Config::default()).context("Failed to serialize default config")?;
+        let mut template = String::new();
+        for (key, value) in defaults.as_table().into_iter().flatten() {
+            let description = CONFIG_DESCRIPTIONS
+                .iter()
+                .find_map(|(name, description)| (name == key).then_some(*description))
+                .with_context(|| format!("Missing description of config key {key}"))?;
+            template.push_str(&format!("# {description}\n{key} = {value}\n\n"));
+        }
+        Ok(template)
+    }
+
+    /// Parses a TOML config from a reader.
+    fn parse<R: std::io::Read>(mut reader: R) -> anyhow::Result<Self> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content).context("Failed to read config")?;
+        let mut c = config::Config::new();
+        c.merge(config::File::from_str(&content, config::FileFormat::Toml))?;
+        Ok(c.try_into()?)
+    }
 }
 
+// Descriptions of the keys in the template printed by `--print-default-config`.
+const CONFIG_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("log_level", "Level of the logs printed on stderr: trace, debug, info, warn or error."),
+    ("input_file", "File to process when no input is given on the command line."),
+];
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -191,13 +897,622 @@ mod tests {
     #[test]
     fn basic_parsing() {
         let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/small.log");
-        let sut = process_file(path).unwrap();
+        let mut sut = LogStats::default();
+        process_file(path, &mut sut, &Args::parse_from(["word-counter"])).unwrap();
         let expected = {
             let mut count_map = HashMap::new();
-            count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76 });
-            count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169 });
-            LogStats { file_len_bytes: 0, start: Instant::now(), count_map }
+            count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+            count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+            LogStats { count_map, ..Default::default() }
         };
         assert_eq!(expected.count_map, sut.count_map);
     }
+
+    #[test]
+    fn self_test_passes() {
+        assert!(self_test().unwrap());
+    }
+
+    #[test]
+    fn self_test_takes_no_options() {
+        for command in ["self-test", "--self-test"] {
+            let args = Args::try_parse_from(["word-counter", command]).unwrap();
+            assert_eq!(Some(Command::SelfTest), args.command);
+            assert!(Args::try_parse_from(["word-counter", command, "--key", "kind"]).is_err());
+        }
+        assert!(Args::try_parse_from(["word-counter", "--key", "kind", "--self-test"]).is_err());
+        assert!(Args::try_parse_from(["word-counter", "input.log", "--self-test"]).is_err());
+        // After other arguments the bare word is an input, so a file named like it can still be counted.
+        let args = Args::try_parse_from(["word-counter", "input.log", "self-test"]).unwrap();
+        assert_eq!(None, args.command);
+        assert_eq!(vec!["input.log", "self-test"], args.files);
+    }
+
+    #[test]
+    fn transitions_dont_cross_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.log");
+        let second = dir.path().join("b.log");
+        std::fs::write(&first, "{\"type\":\"A\"}\n{\"type\":\"B\"}\n").unwrap();
+        std::fs::write(&second, "{\"type\":\"C\"}\n{\"type\":\"A\"}\n").unwrap();
+        let args = Args::parse_from(["word-counter", "--transitions", first.to_str().unwrap(), second.to_str().unwrap()]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        assert_eq!(vec![("A", "B", 1), ("C", "A", 1)], sut.transitions.unwrap().sorted());
+    }
+
+    #[test]
+    fn positional_files_are_aggregated() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.log");
+        let second = dir.path().join("b.log");
+        std::fs::write(&first, "{\"type\":\"A\"}\n{\"type\":\"B\"}\n").unwrap();
+        std::fs::write(&second, "{\"type\":\"A\"}\n").unwrap();
+        let args = Args::parse_from(["word-counter", first.to_str().unwrap(), second.to_str().unwrap()]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        assert_eq!(2, sut.count_map["A"].count);
+        assert_eq!(1, sut.count_map["B"].count);
+    }
+
+    #[test]
+    fn recursive_directory_is_aggregated() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("service").join("2024-01-01");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join("a.log"), "{\"type\":\"A\"}\n").unwrap();
+        std::fs::write(nested.join("b.log"), "{\"type\":\"A\"}\n{\"type\":\"B\"}\n").unwrap();
+        std::fs::write(nested.join("notes.txt"), "{\"type\":\"C\"}\n").unwrap();
+        let args = Args::parse_from(["word-counter", "--recursive", "--ext", "log", dir.path().to_str().unwrap()]);
+        let inputs = walk::expand(args.inputs("unused.log"), &args.ext).unwrap();
+        let sut = process_inputs(&inputs, &args).unwrap();
+        assert_eq!(2, sut.count_map["A"].count);
+        assert_eq!(1, sut.count_map["B"].count);
+        assert!(!sut.count_map.contains_key("C"));
+    }
+
+    #[test]
+    fn content_hash_ignores_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.log");
+        let second = dir.path().join("second.log");
+        let (a1, a2, b) = ("{\"type\":\"A\",\"n\":1}", "{\"type\":\"A\",\"n\":2}", "{\"type\":\"B\"}");
+        std::fs::write(&first, format!("{a1}\n{b}\n{a2}\n")).unwrap();
+        // Reordered, and without the trailing new line.
+        std::fs::write(&second, format!("{a2}\n{a1}\n{b}")).unwrap();
+        let args = Args::parse_from(["word-counter", "--content-hash"]);
+        let first_stats = process_inputs(&[first.to_str().unwrap().to_string()], &args).unwrap();
+        let second_stats = process_inputs(&[second.to_str().unwrap().to_string()], &args).unwrap();
+        assert_ne!(0, first_stats.count_map["A"].content_hash);
+        assert_ne!(first_stats.count_map["A"].content_hash, first_stats.count_map["B"].content_hash);
+        for object_type in ["A", "B"] {
+            let expected = first_stats.count_map[object_type].content_hash;
+            assert_eq!(expected, second_stats.count_map[object_type].content_hash);
+        }
+    }
+
+    #[test]
+    fn access_log_by_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        let lines = [
+            r#"10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 612 "-" "curl/8.0""#,
+            r#"10.0.0.2 - - [10/Oct/2000:13:55:37 -0700] "GET /missing HTTP/1.1" 404 153 "-" "curl/8.0""#,
+            r#"10.0.0.1 - - [10/Oct/2000:13:55:38 -0700] "POST /login HTTP/1.1" 200 20 "-" "curl/8.0""#,
+            "not an access log line",
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        // The status is the default key of the access logs.
+        let args = Args::parse_from(["word-counter", "--format-preset", "nginx"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["200"].count);
+        assert_eq!(1, sut.count_map["404"].count);
+        assert_eq!(2, sut.count_map.len());
+        let args = Args::parse_from(["word-counter", "--format-preset", "nginx", "--key", "method"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["GET"].count);
+    }
+
+    #[test]
+    fn hour_prefix_of_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timestamps.log");
+        let lines = [
+            r#"{"time":"2024-01-02T15:04:05Z"}"#,
+            r#"{"time":"2024-01-02T15:59:59.999+01:00"}"#,
+            r#"{"time":"2024-01-02T16:00:00Z"}"#,
+            r#"{"time":"2024-01-02"}"#,
+            r#"{"time":1704207845}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let args = Args::parse_from(["word-counter", "--time-prefix", "time:13"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["2024-01-02T15"].count);
+        assert_eq!(1, sut.count_map["2024-01-02T16"].count);
+        assert_eq!(2, sut.count_map.len());
+        assert!(token::parse_time_prefix("time:0").is_err());
+    }
+
+    #[test]
+    fn first_token_of_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("messages.log");
+        let lines = [
+            r#"{"message":"Connected to db in 3ms"}"#,
+            r#"{"message":"  Retrying request 12"}"#,
+            r#"{"message":"Connected to cache"}"#,
+            r#"{"message":""}"#,
+            r#"{"message":42}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let args = Args::parse_from(["word-counter", "--token-field", "message:0"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["Connected"].count);
+        assert_eq!(1, sut.count_map["Retrying"].count);
+        assert_eq!(2, sut.count_map.len());
+        let args = Args::parse_from(["word-counter", "--token-field", "message:4"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(1, sut.count_map["3ms"].count);
+        assert_eq!(1, sut.count_map.len());
+    }
+
+    #[test]
+    fn only_lines_containing_substring() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("contains.log");
+        let lines = [
+            r#"{"type":"A","message":"upstream timeout"}"#,
+            r#"{"type":"B","message":"Timeout"}"#,
+            r#"{"type":"A","message":"ok"}"#,
+            "not json but has timeout",
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let args = Args::parse_from(["word-counter", "--contains", "timeout"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(1, sut.count_map["A"].count);
+        assert_eq!(1, sut.count_map.len());
+        let args = Args::parse_from(["word-counter", "--contains", "timeout", "--ignore-case"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(1, sut.count_map["A"].count);
+        assert_eq!(1, sut.count_map["B"].count);
+    }
+
+    #[test]
+    fn joined_fields_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("joined.log");
+        let lines = [
+            r#"{"method":"GET","status":200}"#,
+            r#"{"method":"GET","status":404}"#,
+            r#"{"method":"GET","status":200}"#,
+            r#"{"method":"POST"}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let args = Args::parse_from(["word-counter", "--key-join", "method,status"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["GET 200"].count);
+        assert_eq!(1, sut.count_map["GET 404"].count);
+        assert_eq!(2, sut.count_map.len());
+        let args = Args::parse_from(["word-counter", "--key-join", "status,method", "--key-separator", "/"]);
+        let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        assert_eq!(2, sut.count_map["200/GET"].count);
+    }
+
+    #[test]
+    fn head_of_reversed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reverse.log");
+        let lines = ["A", "A", "B", "C", "C"].map(|object_type| format!("{{\"type\":\"{object_type}\"}}"));
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        let args = Args::parse_from(["word-counter", "--reverse-read", "--head", "3"]);
+        let sut = process_inputs(&inputs, &args).unwrap();
+        assert_eq!(2, sut.count_map["C"].count);
+        assert_eq!(1, sut.count_map["B"].count);
+        assert!(!sut.count_map.contains_key("A"));
+        let args = Args::parse_from(["word-counter", "--head", "3"]);
+        assert_eq!(2, process_inputs(&inputs, &args).unwrap().count_map["A"].count);
+        // Without a limit, the counts are the same in both directions.
+        let forward = process_inputs(&inputs, &Args::parse_from(["word-counter"])).unwrap();
+        let backward = process_inputs(&inputs, &Args::parse_from(["word-counter", "--reverse-read"])).unwrap();
+        assert_eq!(forward.count_map, backward.count_map);
+    }
+
+    #[test]
+    fn unchanged_input_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cached.log");
+        std::fs::write(&path, "{\"type\":\"A\"}\n{\"type\":\"B\"}\n{\"type\":\"A\"}\n").unwrap();
+        let cache_dir = dir.path().join("cache");
+        let inputs = [path.to_str().unwrap().to_string()];
+        let args = Args::parse_from(["word-counter", "--cache-dir", cache_dir.to_str().unwrap()]);
+        let first = process_cached(&inputs, &args).unwrap();
+        assert_eq!(3, first.lines_read);
+        // Nothing is read on a hit.
+        let second = process_cached(&inputs, &args).unwrap();
+        assert_eq!(0, second.lines_read);
+        assert_eq!(first.count_map, second.count_map);
+        let args = Args::parse_from(["word-counter", "--cache-dir", cache_dir.to_str().unwrap(), "--no-cache"]);
+        assert_eq!(3, process_cached(&inputs, &args).unwrap().lines_read);
+
+        std::fs::write(&path, "{\"type\":\"A\"}\n").unwrap();
+        let args = Args::parse_from(["word-counter", "--cache-dir", cache_dir.to_str().unwrap()]);
+        assert_eq!(1, process_cached(&inputs, &args).unwrap().count_map["A"].count);
+    }
+
+    #[test]
+    fn cache_is_shared_by_the_output_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cached.log");
+        std::fs::write(&path, "{\"type\":\"A\",\"ip\":\"1.2.3.4\"}\n").unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        let cache_dir = dir.path().join("cache");
+        let entry_path = |extra: &[&str]| {
+            let args = ["word-counter", "--cache-dir", cache_dir.to_str().unwrap()];
+            let args = Args::parse_from(args.iter().chain(extra));
+            cache::entry_path(&cache_dir, &inputs, &args).unwrap().unwrap()
+        };
+        let table = entry_path(&[]);
+        assert_eq!(table, entry_path(&["--format", "json", "--output", "stats.json", "--columns", "byte_rate"]));
+        assert_ne!(table, entry_path(&["--key", "kind"]));
+        assert_ne!(table, entry_path(&["--head", "10"]));
+
+        let geo_db = dir.path().join("countries.mmdb");
+        let geo_db = geo_db.to_str().unwrap();
+        std::fs::write(geo_db, "first").unwrap();
+        let first = entry_path(&["--geo-field", "ip", "--geo-db", geo_db]);
+        std::fs::write(geo_db, "replaced").unwrap();
+        assert_ne!(first, entry_path(&["--geo-field", "ip", "--geo-db", geo_db]));
+    }
+
+    #[test]
+    fn cached_levels_are_sorted_by_severity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("levels.log");
+        let levels = ["ERROR", "info", "ERROR", "debug", "ERROR"];
+        let content: String = levels.iter().map(|level| format!("{{\"level\":\"{level}\"}}\n")).collect();
+        std::fs::write(&path, content).unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        let cache_dir = dir.path().join("cache");
+        let args = Args::parse_from(["word-counter", "--levels", "--cache-dir", cache_dir.to_str().unwrap()]);
+        let sorted_levels = |stats: &LogStats| {
+            let entries = stats.sorted_entries();
+            entries.into_iter().map(|(level, object_stats)| (level.clone(), object_stats.count)).collect::<Vec<_>>()
+        };
+        let first = process_cached(&inputs, &args).unwrap();
+        let second = process_cached(&inputs, &args).unwrap();
+        assert_eq!(0, second.lines_read);
+        let expected = vec![("DEBUG".to_string(), 1), ("INFO".to_string(), 1), ("ERROR".to_string(), 3)];
+        assert_eq!(expected, sorted_levels(&first));
+        assert_eq!(expected, sorted_levels(&second));
+    }
+
+    #[test]
+    fn header_bytes_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.log");
+        let header = "\x00\x01BINARY HEADER v2\n";
+        std::fs::write(&path, format!("{header}{{\"type\":\"A\"}}\n{{\"type\":\"B\"}}\n{{\"type\":\"A\"}}\n")).unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        // Also in the middle of the header, the rest of its line is skipped.
+        for skip in [header.len(), 4] {
+            for reverse_read in [false, true] {
+                let skip = skip.to_string();
+                let mut args = vec!["word-counter", "--skip-header-bytes", &skip];
+                if reverse_read {
+                    args.push("--reverse-read");
+                }
+                let sut = process_inputs(&inputs, &Args::parse_from(args)).unwrap();
+                assert_eq!(2, sut.count_map["A"].count);
+                assert_eq!(1, sut.count_map["B"].count);
+                assert_eq!(3, sut.lines_read);
+            }
+        }
+    }
+
+    #[test]
+    fn auto_collapse_counts_the_tail_as_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("distinct.log");
+        let mut content = "{\"type\":\"A\"}\n{\"type\":\"B\"}\n".to_string();
+        for index in 0..100 {
+            content.push_str(&format!("{{\"type\":\"tail-{index}\"}}\n{{\"type\":\"A\"}}\n"));
+        }
+        std::fs::write(&path, content).unwrap();
+        let args = Args::parse_from(["word-counter", "--auto-collapse-after", "3"]);
+        let stats = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        let counts: HashMap<_, _> = stats.count_map.iter().map(|(key, stats)| (key.as_str(), stats.count)).collect();
+        let expected = HashMap::from([("A", 101), ("B", 1), ("tail-0", 1), (OTHER_TYPE, 99)]);
+        assert_eq!(expected, counts);
+    }
+
+    #[test]
+    fn auto_collapse_splits_other_by_bool() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("distinct.log");
+        let mut content = "{\"type\":\"A\",\"ok\":true}\n{\"type\":\"B\",\"ok\":false}\n".to_string();
+        for index in 0..10 {
+            content.push_str(&format!("{{\"type\":\"tail-{index}\",\"ok\":{}}}\n", index % 3 == 0));
+        }
+        std::fs::write(&path, content).unwrap();
+        let args = Args::parse_from(["word-counter", "--split-bool", "ok", "--auto-collapse-after", "2"]);
+        let stats = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        let bool_split = stats.bool_split.unwrap();
+        let other = bool_split.counts(OTHER_TYPE);
+        assert_eq!((4, 6, 0), (other.true_count, other.false_count, other.unknown));
+        assert_eq!(0, bool_split.counts("tail-0").true_count);
+        assert_eq!(1, bool_split.counts("A").true_count);
+    }
+
+    #[test]
+    fn sampled_counts_are_scaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sampled.log");
+        std::fs::write(&path, "{\"type\":\"A\"}\n".repeat(10_000)).unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        let count = |args: &[&str]| {
+            let args = Args::parse_from(["word-counter"].iter().chain(args));
+            process_inputs(&inputs, &args).unwrap().count_map["A"].count
+        };
+        assert_eq!(10_000, count(&["--sample-rate", "1.0"]));
+        let estimate = count(&["--sample-rate", "0.1", "--seed", "42"]);
+        assert_eq!(estimate, count(&["--sample-rate", "0.1", "--seed", "42"]));
+        assert_eq!(0, estimate % 10);
+        assert!((9_000..11_000).contains(&estimate));
+    }
+
+    #[test]
+    fn levels_sorted_by_severity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("levels.log");
+        let levels = ["info", "ERROR", "notice", "debug", "INFO", "warning", "info", "fatal", "notice"];
+        let content: String = levels.iter().map(|level| format!("{{\"level\":\"{level}\"}}\n")).collect();
+        std::fs::write(&path, content).unwrap();
+        let args = Args::parse_from(["word-counter", "--levels"]);
+        let stats = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+        let sorted: Vec<_> = stats
+            .sorted_entries()
+            .into_iter()
+            .map(|(level, object_stats)| (level.as_str(), object_stats.count))
+            .collect();
+        let expected = vec![("DEBUG", 1), ("INFO", 3), ("WARN", 1), ("ERROR", 1), ("FATAL", 1), ("notice", 2)];
+        assert_eq!(expected, sorted);
+    }
+
+    #[test]
+    fn growing_file_is_warned() {
+        use std::io::Write;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("growing.log");
+        std::fs::write(&path, "{\"type\":\"A\"}\n").unwrap();
+        let path = path.to_str().unwrap();
+        let initial_len = std::fs::metadata(path).unwrap().len();
+        assert!(!warn_if_grown(path, initial_len).unwrap());
+        // Another process appending while we count.
+        let mut writer = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        writeln!(writer, "{{\"type\":\"B\"}}").unwrap();
+        assert!(warn_if_grown(path, initial_len).unwrap());
+    }
+
+    #[test]
+    fn resume_from_mid_file_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("resume.log");
+        let checkpoint_path = dir.path().join("checkpoint");
+        let content = "{\"type\":\"A\"}\n{\"type\":\"A\"}\n{\"type\":\"B\"}\n{\"type\":\"C\"}\n";
+        std::fs::write(&path, content).unwrap();
+        // Offset 16 is in the middle of the second line, which is skipped as well.
+        let args = Args::parse_from([
+            "word-counter",
+            "--resume-from",
+            "16",
+            "--checkpoint",
+            checkpoint_path.to_str().unwrap(),
+            path.to_str().unwrap(),
+        ]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        assert!(!sut.count_map.contains_key("A"));
+        assert_eq!(1, sut.count_map["B"].count);
+        assert_eq!(1, sut.count_map["C"].count);
+        assert_eq!(content.len().to_string(), std::fs::read_to_string(&checkpoint_path).unwrap());
+
+        let inputs = [path.to_str().unwrap().to_string(), path.to_str().unwrap().to_string()];
+        let args = Args::parse_from(["word-counter", "--checkpoint", checkpoint_path.to_str().unwrap()]);
+        assert!(process_inputs(&inputs, &args).is_err());
+    }
+
+    #[test]
+    fn root_path_counts_nested_array() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response.json");
+        let document = serde_json::json!({"data": {"items": [{"kind": "A"}, {"kind": "B"}, {"kind": "A"}, {"other": 1}]}});
+        std::fs::write(&path, serde_json::to_string_pretty(&document).unwrap()).unwrap();
+        let args = Args::parse_from(["word-counter", "--root-path", "/data/items", "--key", "kind", path.to_str().unwrap()]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        assert_eq!(2, sut.count_map["A"].count);
+        assert_eq!(1, sut.count_map["B"].count);
+        assert_eq!(r#"{"kind":"A"}"#.len() * 2, sut.count_map["A"].bytes);
+        assert_eq!(2, sut.count_map.len());
+    }
+
+    #[test]
+    fn root_path_cant_be_resumed() {
+        let parse = |extra: &[&str]| {
+            Args::try_parse_from(["word-counter", "--root-path", "/data/items"].iter().chain(extra))
+        };
+        assert!(parse(&[]).is_ok());
+        assert!(parse(&["--resume-from", "10"]).is_err());
+        assert!(parse(&["--checkpoint", "offset"]).is_err());
+    }
+
+    #[test]
+    fn root_path_elements_are_filtered_by_contains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("response.json");
+        let items = [serde_json::json!({"kind": "A", "msg": "ok"}), serde_json::json!({"kind": "B", "msg": "ZZZ"})];
+        let document = serde_json::json!({"data": {"items": items}});
+        std::fs::write(&path, serde_json::to_string_pretty(&document).unwrap()).unwrap();
+        let count = |extra: &[&str]| {
+            let args = ["word-counter", "--root-path", "/data/items", "--key", "kind"];
+            let args = Args::parse_from(args.iter().chain(extra));
+            let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+            sut.count_map.into_iter().map(|(key, stats)| (key, stats.count)).collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(BTreeMap::from([("B".to_string(), 1)]), count(&["--contains", "zzz", "--ignore-case"]));
+        assert!(count(&["--contains", "nowhere"]).is_empty());
+    }
+
+    #[test]
+    fn root_path_elements_are_sampled_like_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let items: Vec<_> =
+            (0..1_000).map(|index| serde_json::json!({"type": if index % 3 == 0 { "B" } else { "A" }})).collect();
+        let document_path = dir.path().join("response.json");
+        std::fs::write(&document_path, serde_json::json!({"data": {"items": items}}).to_string()).unwrap();
+        let lines_path = dir.path().join("items.log");
+        std::fs::write(&lines_path, items.iter().map(|item| format!("{item}\n")).collect::<String>()).unwrap();
+        let count = |path: &Path, extra: &[&str]| {
+            let args = Args::parse_from(["word-counter", "--sample-rate", "0.1", "--seed", "42"].iter().chain(extra));
+            let sut = process_inputs(&[path.to_str().unwrap().to_string()], &args).unwrap();
+            (sut.count_map["A"].count, sut.count_map["B"].count)
+        };
+        let (a, b) = count(&document_path, &["--root-path", "/data/items"]);
+        assert_eq!(count(&lines_path, &[]), (a, b));
+        assert!((550..800).contains(&a), "{a}");
+        assert!((250..420).contains(&b), "{b}");
+    }
+
+    #[test]
+    fn gzip_input_is_decompressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("small.log.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+        let mut small_log = File::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/small.log")).unwrap();
+        std::io::copy(&mut small_log, &mut encoder).unwrap();
+        encoder.finish().unwrap();
+        let args = Args::parse_from(["word-counter", "--progress", path.to_str().unwrap()]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        assert_eq!(3, sut.count_map["A"].count);
+        assert_eq!(4, sut.count_map["B"].count);
+    }
+
+    #[test]
+    fn parallel_gzip_matches_serial() {
+        use std::io::Write;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("many.log.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::default());
+        // Several chunks worth of lines, so some of them are split between two chunks.
+        for index in 0..100_000 {
+            writeln!(encoder, "{{\"type\":\"type-{}\",\"index\":{index}}}", index % 7).unwrap();
+        }
+        encoder.finish().unwrap();
+        let inputs = [path.to_str().unwrap().to_string()];
+        let serial = process_inputs(&inputs, &Args::parse_from(["word-counter"])).unwrap();
+        let parallel = process_inputs(&inputs, &Args::parse_from(["word-counter", "--parallel-gzip"])).unwrap();
+        assert_eq!(7, parallel.count_map.len());
+        assert_eq!(serial.count_map, parallel.count_map);
+    }
+
+    #[derive(Clone, Default)]
+    struct SpanCapture(std::sync::Arc<std::sync::Mutex<Vec<HashMap<String, String>>>>);
+
+    struct FieldsVisitor(HashMap<String, String>);
+
+    impl tracing::field::Visit for FieldsVisitor {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{value:?}"));
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanCapture {
+        fn new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut visitor = FieldsVisitor(HashMap::new());
+            attrs.record(&mut visitor);
+            self.0.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    #[test]
+    fn one_span_per_type() {
+        use tracing_subscriber::layer::SubscriberExt;
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+        let stats = LogStats { count_map, ..Default::default() };
+        let capture = SpanCapture::default();
+        let subscriber = tracing_subscriber::Registry::default().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || stats.emit_spans());
+        let spans = capture.0.lock().unwrap();
+        assert_eq!(2, spans.len());
+        let span_a = spans.iter().find(|span| span["object_type"] == "A").unwrap();
+        assert_eq!("3", span_a["count"]);
+        assert_eq!("76", span_a["bytes"]);
+    }
+
+    #[test]
+    fn transitions_of_small_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/small.log");
+        let args = Args::parse_from(["word-counter", "--transitions"]);
+        let mut sut = LogStats::new(&args).unwrap();
+        process_file(path, &mut sut, &args).unwrap();
+        let expected = vec![("B", "A", 3), ("A", "B", 2), ("B", "B", 1)];
+        assert_eq!(expected, sut.transitions.unwrap().sorted());
+    }
+
+    #[test]
+    fn counts_by_file_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.log");
+        let new = dir.path().join("new.log");
+        std::fs::write(&old, "{\"type\":\"A\"}\n{\"type\":\"A\"}\n").unwrap();
+        std::fs::write(&new, "{\"type\":\"A\"}\n{\"type\":\"B\"}\n").unwrap();
+        // 2024-01-02T00:00:00Z and a day later.
+        let day = Duration::from_secs(86_400);
+        let old_mtime = std::time::UNIX_EPOCH + Duration::from_secs(1_704_153_600);
+        File::options().write(true).open(&old).unwrap().set_modified(old_mtime).unwrap();
+        File::options().write(true).open(&new).unwrap().set_modified(old_mtime + day).unwrap();
+        let args = Args::parse_from(["word-counter", "--by-file-date", old.to_str().unwrap(), new.to_str().unwrap()]);
+        let sut = process_inputs(&args.inputs("unused.log"), &args).unwrap();
+        let expected = vec![("2024-01-02", "A", 2), ("2024-01-03", "A", 1), ("2024-01-03", "B", 1)];
+        assert_eq!(expected, sut.file_dates.unwrap().sorted());
+    }
+
+    #[test]
+    fn config_from_reader() {
+        let toml = "log_level = \"warn\"\ninput_file = \"from_stdin.log\"\n";
+        let sut = Config::parse(toml.as_bytes()).unwrap();
+        assert_eq!("warn", sut.log_level);
+        assert_eq!("from_stdin.log", sut.input_file);
+        // Missing keys keep their defaults.
+        assert_eq!(Config::default().log_level, Config::parse("input_file = \"x.log\"".as_bytes()).unwrap().log_level);
+    }
+
+    #[test]
+    fn missing_config_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("does-not-exist.toml");
+        assert!(Config::new(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn default_config_template_round_trip() {
+        let template = Config::default_template().unwrap();
+        assert!(template.lines().any(|line| line.starts_with("# ")));
+        assert_eq!(Config::default(), Config::parse(template.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn byte_rate_uses_elapsed() {
+        let object_stats = ObjectStats { count: 3, bytes: 76, content_hash: 0 };
+        assert_eq!(Some(38.0), object_stats.byte_rate(Duration::from_secs(2)));
+        assert_eq!(None, object_stats.byte_rate(Duration::ZERO));
+    }
 }
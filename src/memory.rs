@@ -0,0 +1,74 @@
+//! Resident memory sampling to verify the input is streamed rather than buffered.
+
+use anyhow::Context;
+
+/// How many lines are processed between two memory samples. Reading the RSS is a syscall, so we don't want it per line.
+pub const SAMPLE_INTERVAL_LINES: usize = 65_536;
+
+// Generous estimate of what a single entry in the `count_map` takes, so a high cardinality doesn't trip the guard.
+const BYTES_PER_KEY: u64 = 1024;
+
+pub struct MemoryGuard {
+    baseline_bytes: u64,
+    bound_bytes: u64,
+}
+
+impl MemoryGuard {
+    /// Takes the current RSS as the baseline the growth is measured against.
+    pub fn new(bound_mb: u64) -> anyhow::Result<Self> {
+        Ok(Self {
+            baseline_bytes: resident_bytes()?,
+            bound_bytes: bound_mb * 1_048_576,
+        })
+    }
+
+    /// Fails if the RSS grew more than the bound plus the allowance for the distinct keys seen so far.
+    pub fn check(&self, distinct_keys: usize) -> anyhow::Result<()> {
+        let current_bytes = resident_bytes()?;
+        let growth_bytes = current_bytes.saturating_sub(self.baseline_bytes);
+        let allowed_bytes = self.bound_bytes + distinct_keys as u64 * BYTES_PER_KEY;
+        tracing::debug!("[rss={current_bytes}][growth={growth_bytes}][allowed={allowed_bytes}]");
+        if growth_bytes > allowed_bytes {
+            anyhow::bail!(
+                "Memory grew by {} MB while streaming, more than the allowed {} MB for {distinct_keys} distinct types",
+                growth_bytes / 1_048_576,
+                allowed_bytes / 1_048_576
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> anyhow::Result<u64> {
+    // `status` reports the RSS in kB, unlike `statm` whose pages depend on the page size of the kernel.
+    let status = std::fs::read_to_string("/proc/self/status").context("Failed to read /proc/self/status")?;
+    vm_rss_bytes(&status).context("Unexpected /proc/self/status format")
+}
+
+// The `VmRSS:   1234 kB` line of `/proc/self/status`.
+#[cfg(target_os = "linux")]
+fn vm_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kilobytes: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> anyhow::Result<u64> {
+    anyhow::bail!("Memory sampling is only supported on Linux")
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rss_read_from_status() {
+        let status = "Name:\tword-counter\nVmPeak:\t   20000 kB\nVmRSS:\t    5120 kB\nThreads:\t1\n";
+        assert_eq!(Some(5120 * 1024), vm_rss_bytes(status));
+        assert_eq!(None, vm_rss_bytes("Name:\tword-counter\n"));
+        assert!(resident_bytes().unwrap() > 0);
+    }
+}
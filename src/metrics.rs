@@ -0,0 +1,69 @@
+//! Single number summaries of the distribution of counts across types.
+
+/// Gini coefficient of the counts: 0 when every type has the same count, approaching 1 when a single type dominates.
+///
+/// With the counts sorted ascending as `x_1..x_n`, it's `2 * sum(i * x_i) / (n * sum(x_i)) - (n + 1) / n`.
+pub fn gini(counts: impl IntoIterator<Item = usize>) -> Option<f64> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    counts.sort_unstable();
+    let n = counts.len() as f64;
+    let weighted_sum: f64 = counts.iter().enumerate().map(|(i, count)| (i + 1) as f64 * *count as f64).sum();
+    Some(2.0 * weighted_sum / (n * total as f64) - (n + 1.0) / n)
+}
+
+/// Counts further than `sigmas` standard deviations from the mean count of all the types.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outliers {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub sigmas: f64,
+}
+
+impl Outliers {
+    pub fn new(counts: impl IntoIterator<Item = usize>, sigmas: f64) -> Option<Self> {
+        let counts: Vec<_> = counts.into_iter().map(|count| count as f64).collect();
+        if counts.is_empty() {
+            return None;
+        }
+        let n = counts.len() as f64;
+        let mean = counts.iter().sum::<f64>() / n;
+        // Population standard deviation, the types seen are all the types there are.
+        let variance = counts.iter().map(|count| (count - mean).powi(2)).sum::<f64>() / n;
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+            sigmas,
+        })
+    }
+
+    pub fn is_outlier(&self, count: usize) -> bool {
+        (count as f64 - self.mean).abs() > self.sigmas * self.std_dev
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gini_of_known_distributions() {
+        assert_eq!(Some(0.0), gini([5, 5, 5, 5]));
+        assert_eq!(Some(0.25), gini([4, 1, 3, 2]));
+        assert_eq!(Some(0.75), gini([0, 10, 0, 0]));
+        assert_eq!(None, gini([]));
+    }
+
+    #[test]
+    fn dominant_type_is_outlier() {
+        let counts: Vec<_> = std::iter::once(1_000).chain(std::iter::repeat_n(10, 20)).collect();
+        let sut = Outliers::new(counts, 3.0).unwrap();
+        assert!(sut.is_outlier(1_000));
+        assert!(!sut.is_outlier(10));
+        assert!(!Outliers::new([7, 7, 7], 3.0).unwrap().is_outlier(7));
+        assert_eq!(None, Outliers::new([], 3.0));
+    }
+}
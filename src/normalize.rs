@@ -0,0 +1,177 @@
+//! Rewrites of the counted value before bucketing, to merge variants that should be counted together.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::cli::Args;
+
+/// Placeholder replacing the ids found by `--collapse-uuids`.
+pub const ID_PLACEHOLDER: &str = "<id>";
+
+/// Applies the normalizations enabled in `args` to the value of the counted field.
+pub fn object_type<'a>(value: &'a str, args: &Args) -> Cow<'a, str> {
+    let mut value = Cow::Borrowed(value);
+    if args.collapse_uuids {
+        value = collapse_ids(value);
+    }
+    if args.merge_plurals {
+        value = singularize(value);
+    }
+    value
+}
+
+// Canonical UUIDs and runs of at least 16 hex digits, the length of a 64 bits id. Shorter hex-looking words like `cafe`
+// or `added` are left alone.
+fn id_regex() -> &'static Regex {
+    static ID_REGEX: OnceLock<Regex> = OnceLock::new();
+    ID_REGEX.get_or_init(|| {
+        Regex::new(r"(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}|[0-9a-f]{16,}").unwrap()
+    })
+}
+
+fn collapse_ids(value: Cow<str>) -> Cow<str> {
+    let mut collapsed = String::new();
+    let mut copied = 0;
+    for id in id_regex().find_iter(&value).filter(|id| is_whole_word(&value, id.start(), id.end())) {
+        collapsed.push_str(&value[copied..id.start()]);
+        collapsed.push_str(ID_PLACEHOLDER);
+        copied = id.end();
+    }
+    if copied == 0 {
+        return value;
+    }
+    collapsed.push_str(&value[copied..]);
+    Cow::Owned(collapsed)
+}
+
+// Only whole words are ids. Unlike regex's `\b`, `_` separates words too, as in `session_<uuid>`.
+fn is_whole_word(value: &str, start: usize, end: usize) -> bool {
+    let before = value[..start].chars().next_back();
+    let after = value[end..].chars().next();
+    !before.is_some_and(|c| c.is_ascii_alphanumeric()) && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+}
+
+// Words that end like plurals but aren't, or whose plural isn't built with an `s`.
+const UNCOUNTABLE: &[&str] = &["news", "series", "species", "data", "metadata"];
+const IRREGULAR: &[(&str, &str)] = &[("people", "person"), ("children", "child"), ("men", "man"), ("women", "woman")];
+const ES_SUFFIXES: &[&str] = &["sses", "shes", "ches", "xes", "zzes"];
+// Words whose plural would otherwise match a longer rule, so only the `s` is removed: singulars ending in `e` after
+// one of the `ES_SUFFIXES` or `us`, in `ie` rather than `y`, and in `a` despite the `as` words that aren't plurals.
+const E_SINGULARS: &[&str] = &["cache", "niche", "headache", "avalanche", "use", "fuse", "abuse", "excuse", "ruse"];
+const IE_SINGULARS: &[&str] = &["cookie", "movie", "pie", "tie", "lie", "die", "zombie", "rookie", "selfie", "calorie"];
+const A_SINGULARS: &[&str] = &["idea", "area", "schema", "replica", "quota", "delta", "lambda", "camera", "persona"];
+const NOT_PLURAL_SUFFIXES: &[&str] = &["ss", "us", "is", "as"];
+
+/// Replaces the last word of the value with its singular form, so `users`, `user` and `user_events`, `user_event` are
+/// merged. It's a small set of English rules rather than a full inflector, good enough for event names.
+fn singularize(value: Cow<str>) -> Cow<str> {
+    let word_start = value
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !c.is_alphabetic())
+        .map(|(index, c)| index + c.len_utf8())
+        .unwrap_or(0);
+    match singular_word(&value[word_start..]) {
+        Some(singular) => Cow::Owned(format!("{}{singular}", &value[..word_start])),
+        None => value,
+    }
+}
+
+// `None` if the word isn't a plural.
+fn singular_word(word: &str) -> Option<String> {
+    let lowercase = word.to_lowercase();
+    if UNCOUNTABLE.contains(&lowercase.as_str()) {
+        None
+    } else if let Some((_, singular)) = IRREGULAR.iter().find(|(plural, _)| *plural == lowercase) {
+        // Irregular plurals don't share a prefix with their singular, so only the capitalization is kept.
+        match word.starts_with(char::is_uppercase) {
+            true => Some(singular[..1].to_uppercase() + &singular[1..]),
+            false => Some(singular.to_string()),
+        }
+    } else if E_SINGULARS
+        .iter()
+        .chain(IE_SINGULARS)
+        .chain(A_SINGULARS)
+        .any(|singular| lowercase.strip_suffix('s') == Some(singular))
+    {
+        Some(word[..word.len() - 1].to_string())
+    } else if lowercase.len() > 3 && lowercase.ends_with("ies") {
+        let y = if word.ends_with("IES") { "Y" } else { "y" };
+        Some(format!("{}{y}", &word[..word.len() - 3]))
+    } else if ES_SUFFIXES.iter().any(|suffix| lowercase.ends_with(suffix)) {
+        Some(word[..word.len() - 2].to_string())
+    } else if lowercase.ends_with("uses") && !lowercase[..lowercase.len() - 4].ends_with(['a', 'e', 'i', 'o', 'u']) {
+        // `statuses`, `viruses`, but not `causes` or `houses`.
+        Some(word[..word.len() - 2].to_string())
+    } else if NOT_PLURAL_SUFFIXES.iter().any(|suffix| lowercase.ends_with(suffix)) {
+        None
+    } else if lowercase.len() > 1 && lowercase.ends_with('s') {
+        Some(word[..word.len() - 1].to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_variants_are_merged() {
+        let values = [
+            "session-3f2b8c1e-9a4d-4e1b-8f6a-2c7d9e0b1a34",
+            "session-A1B2C3D4-E5F6-4A7B-8C9D-0E1F2A3B4C5D",
+            "session-00000000-0000-0000-0000-000000000000",
+        ];
+        for value in values {
+            assert_eq!("session-<id>", collapse_ids(Cow::Borrowed(value)));
+        }
+        assert_eq!("trace-<id>-end", collapse_ids(Cow::Borrowed("trace-0123456789abcdef0123-end")));
+        assert_eq!("session_<id>", collapse_ids(Cow::Borrowed("session_3f2b8c1e-9a4d-4e1b-8f6a-2c7d9e0b1a34")));
+        assert_eq!("user_<id>_login", collapse_ids(Cow::Borrowed("user_0123456789abcdef_login")));
+        assert_eq!("<id>-<id>", collapse_ids(Cow::Borrowed("0123456789abcdef-fedcba9876543210")));
+    }
+
+    #[test]
+    fn words_are_not_ids() {
+        for value in ["session-cafe", "decade-added", "deadbeef", "abc0123456789abcdefz"] {
+            assert!(matches!(collapse_ids(Cow::Borrowed(value)), Cow::Borrowed(_)), "{value}");
+        }
+    }
+
+    #[test]
+    fn plurals_are_merged_into_singular() {
+        let cases = [
+            ("user", "user"),
+            ("users", "user"),
+            ("requests", "request"),
+            ("user_events", "user_event"),
+            ("policies", "policy"),
+            ("boxes", "box"),
+            ("classes", "class"),
+            ("status", "status"),
+            ("statuses", "status"),
+            ("cache", "cache"),
+            ("caches", "cache"),
+            ("cache_misses", "cache_miss"),
+            ("matches", "match"),
+            ("causes", "cause"),
+            ("uses", "use"),
+            ("alias", "alias"),
+            ("canvas", "canvas"),
+            ("schemas", "schema"),
+            ("cookies", "cookie"),
+            ("movies", "movie"),
+            ("pies", "pie"),
+            ("news", "news"),
+            ("people", "person"),
+            ("Users", "User"),
+            ("http-requests", "http-request"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(expected, singularize(Cow::Borrowed(value)), "{value}");
+        }
+    }
+}
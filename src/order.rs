@@ -0,0 +1,73 @@
+//! Detection of lines whose timestamp goes back in time, for `--check-order`.
+
+use chrono::DateTime;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct OrderCheck {
+    pub field: String,
+    pub out_of_order: usize,
+    /// Largest difference between a line's timestamp and the previous one, when the former is older.
+    pub worst_backward_jump: f64,
+    previous: Option<f64>,
+}
+
+impl OrderCheck {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Compares the line's timestamp with the previous line's. Lines without a timestamp are ignored.
+    pub fn record(&mut self, line: &serde_json::Value) {
+        let Some(timestamp) = line.get(&self.field).and_then(timestamp) else {
+            return;
+        };
+        if let Some(previous) = self.previous {
+            if timestamp < previous {
+                self.out_of_order += 1;
+                self.worst_backward_jump = self.worst_backward_jump.max(previous - timestamp);
+            }
+        }
+        self.previous = Some(timestamp);
+    }
+}
+
+/// Numbers are compared as they are, in whatever unit they come. RFC 3339 strings are converted to seconds.
+pub fn timestamp(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(string) => DateTime::parse_from_rfc3339(string)
+            .ok()
+            .map(|datetime| datetime.timestamp_millis() as f64 / 1000.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backward_timestamps_are_reported() {
+        let mut sut = OrderCheck::new("ts");
+        for ts in ["2024-01-02T15:00:00Z", "2024-01-02T15:00:10Z", "2024-01-02T15:00:04Z", "2024-01-02T15:00:20Z"] {
+            sut.record(&serde_json::json!({ "ts": ts }));
+        }
+        sut.record(&serde_json::json!({"no_ts": 1}));
+        sut.record(&serde_json::json!({"ts": "2024-01-02T14:59:50+00:00"}));
+        assert_eq!(2, sut.out_of_order);
+        assert_eq!(30.0, sut.worst_backward_jump);
+    }
+
+    #[test]
+    fn numeric_timestamps() {
+        let mut sut = OrderCheck::new("ts");
+        for ts in [100, 200, 150, 300] {
+            sut.record(&serde_json::json!({ "ts": ts }));
+        }
+        assert_eq!(1, sut.out_of_order);
+        assert_eq!(50.0, sut.worst_backward_jump);
+    }
+}
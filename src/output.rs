@@ -0,0 +1,721 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use cli_table::Style;
+
+use crate::cli::{Args, Column, OutputFormat};
+use crate::file_dates::FileDates;
+use crate::heavy_hitters::HeavyHitters;
+use crate::histogram::Histogram;
+use crate::metrics::Outliers;
+use crate::transitions::Transitions;
+use crate::{budget, color, metrics, sample, severity, snapshot, LogStats, ObjectStats};
+
+impl Column {
+    fn title(&self) -> &'static str {
+        match self {
+            Column::ByteRate => "Bytes/s",
+        }
+    }
+}
+
+const VEGA_LITE_SCHEMA: &str = "https://vega.github.io/schema/vega-lite/v5.json";
+
+/// Renders the stats in the format requested by the user.
+pub fn render(stats: &LogStats, args: &Args) -> anyhow::Result<String> {
+    // The modes that count something other than the types have a table of their own, clap rejects them with `--format`.
+    if let Some(histogram) = &stats.histogram {
+        return histogram_table(histogram);
+    }
+    if let Some(file_dates) = &stats.file_dates {
+        if args.cumulative {
+            return file_dates_table(&file_dates.cumulative());
+        }
+        return file_dates_table(file_dates);
+    }
+    if let Some(heavy_hitters) = &stats.heavy_hitters {
+        return heavy_hitters_table(heavy_hitters);
+    }
+    match args.format {
+        OutputFormat::Table => table(stats, args),
+        OutputFormat::Vega => vega(stats, args),
+        OutputFormat::Json => json(stats, args),
+        OutputFormat::Influx => Ok(influx(stats, args, unix_nanos())),
+        OutputFormat::Plain => Ok(plain(stats, args)),
+        OutputFormat::Canonical => Ok(canonical(stats)),
+        OutputFormat::Markdown => Ok(markdown(stats, args)),
+        OutputFormat::Properties => Ok(properties(stats)),
+        OutputFormat::Env => Ok(env(stats)),
+        OutputFormat::Snapshot => Ok(snapshot::encode(&stats.count_map)? + "\n"),
+    }
+}
+
+/// Findings of the checks that don't fit in the stats table, printed after it.
+pub fn reports(stats: &LogStats, args: &Args) -> anyhow::Result<String> {
+    let mut reports = String::new();
+    if let Some(rate) = args.sample_rate {
+        reports.push_str(&format!("Counts and sizes are estimates from a sample of the lines (rate {rate})\n"));
+    }
+    if args.gini {
+        match metrics::gini(stats.count_map.values().map(|object_stats| object_stats.count)) {
+            Some(gini) => reports.push_str(&format!("Gini coefficient: {gini:.4}\n")),
+            None => reports.push_str("Gini coefficient: -\n"),
+        }
+    }
+    if let Some(order_check) = &stats.order_check {
+        reports.push_str(&format!("Out of order lines by {}: {}", order_check.field, order_check.out_of_order));
+        if order_check.out_of_order > 0 {
+            reports.push_str(&format!(" (worst backward jump: {})", order_check.worst_backward_jump));
+        }
+        reports.push('\n');
+    }
+    if let Some(schema_changes) = &stats.schema_changes {
+        if schema_changes.changes.is_empty() {
+            reports.push_str("Schema changes: none\n");
+        }
+        for change in &schema_changes.changes {
+            reports.push_str(&format!(
+                "Schema changed between lines {} and {}: added [{}], removed [{}]\n",
+                change.from_line,
+                change.to_line,
+                change.added.join(", "),
+                change.removed.join(", ")
+            ));
+        }
+    }
+    if let Some(extremes) = &stats.extremes {
+        for (title, example) in [("Longest", &extremes.longest), ("Shortest", &extremes.shortest)] {
+            if let Some(example) = example {
+                reports.push_str(&format!(
+                    "{title} line ({} bytes, type {}): {}\n",
+                    example.bytes, example.object_type, example.line
+                ));
+            }
+        }
+    }
+    for check in budget::check(&args.budgets, &stats.count_map, stats.rate_window()) {
+        reports.push_str(&format!("{check}\n"));
+    }
+    if let Some(transitions) = &stats.transitions {
+        reports.push_str(&transitions_table(transitions)?);
+    }
+    Ok(reports)
+}
+
+fn transitions_table(transitions: &Transitions) -> anyhow::Result<String> {
+    use cli_table::{format::Justify, Cell, Table};
+    let rows: Vec<_> = transitions
+        .sorted()
+        .into_iter()
+        .map(|(from, to, count)| {
+            vec![from.cell().justify(Justify::Right), to.cell().justify(Justify::Right), count.cell().justify(Justify::Right)]
+        })
+        .collect();
+    let table = rows
+        .table()
+        .title(vec!["From".cell().bold(true), "To".cell().bold(true), "Count".cell().bold(true)]);
+    let display = table.display().context("Failed to render transitions table")?;
+    Ok(format!("{display}\n"))
+}
+
+fn table(stats: &LogStats, args: &Args) -> anyhow::Result<String> {
+    use cli_table::{format::Justify, Cell, Color, Table};
+    let outliers = outliers(stats, args);
+    let mut rows = vec![];
+    for (object_type, object_stats) in stats.sorted_entries() {
+        let type_color = args.color_by_type.then(|| Color::Ansi256(color::type_color(object_type)));
+        let mut row = vec![
+            type_label(object_type, args).cell().justify(Justify::Right).foreground_color(type_color),
+            object_stats.count.cell().justify(Justify::Right),
+            object_stats.bytes.cell().justify(Justify::Right),
+        ];
+        for cell in extra_cells(stats, args, outliers, object_type, object_stats) {
+            row.push(cell.cell().justify(Justify::Right));
+        }
+        rows.push(row);
+    }
+    let title: Vec<_> = ["Type", "Count", "Size Bytes"]
+        .into_iter()
+        .chain(extra_titles(args))
+        .map(|title| title.cell().bold(true))
+        .collect();
+    let table = rows.table().title(title);
+    let display = table.display().context("Failed to render stats table")?;
+    Ok(format!("{display}\n"))
+}
+
+/// The same columns as the table, but without borders nor colors, for plain text logs.
+fn plain(stats: &LogStats, args: &Args) -> String {
+    let title = ["Type", "Count", "Size Bytes"].into_iter().chain(extra_titles(args)).map(String::from).collect();
+    let outliers = outliers(stats, args);
+    let mut rows = vec![title];
+    for (object_type, object_stats) in stats.sorted_entries() {
+        let mut row = vec![object_type.clone(), object_stats.count.to_string(), object_stats.bytes.to_string()];
+        row.extend(extra_cells(stats, args, outliers, object_type, object_stats));
+        rows.push(row);
+    }
+    aligned(&rows)
+}
+
+/// The same columns as the table as a GitHub flavored Markdown table.
+fn markdown(stats: &LogStats, args: &Args) -> String {
+    let title: Vec<_> = ["Type", "Count", "Size Bytes"].into_iter().chain(extra_titles(args)).collect();
+    let mut output = format!("| {} |\n|{}\n", title.join(" | "), "---|".repeat(title.len()));
+    let outliers = outliers(stats, args);
+    for (object_type, object_stats) in stats.sorted_entries() {
+        let mut row = vec![escape_markdown(object_type), object_stats.count.to_string()];
+        row.push(object_stats.bytes.to_string());
+        row.extend(extra_cells(stats, args, outliers, object_type, object_stats));
+        output.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    output
+}
+
+/// Escapes the characters that would end the cell or the row, or be read as an escape.
+fn escape_markdown(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Titles of the optional columns that follow the type, count and size.
+fn extra_titles(args: &Args) -> Vec<&'static str> {
+    let mut titles: Vec<_> = args.columns.iter().map(Column::title).collect();
+    if args.with_ci {
+        titles.push("95% CI");
+    }
+    if args.split_bool.is_some() {
+        titles.extend(["True", "False", "Unknown"]);
+    }
+    if args.unique_lines {
+        titles.push("Unique");
+    }
+    if args.content_hash {
+        titles.push("Content Hash");
+    }
+    if !args.severity_rules.is_empty() {
+        titles.push("Severity");
+    }
+    if args.flag_outliers {
+        titles.push("Outlier");
+    }
+    if args.trend.is_some() {
+        titles.push("Trend");
+    }
+    titles
+}
+
+/// Distribution the `--flag-outliers` are found in, `None` if disabled.
+pub fn outliers(stats: &LogStats, args: &Args) -> Option<Outliers> {
+    if !args.flag_outliers {
+        return None;
+    }
+    Outliers::new(stats.count_map.values().map(|object_stats| object_stats.count), args.outlier_sigmas)
+}
+
+fn extra_cells(
+    stats: &LogStats,
+    args: &Args,
+    outliers: Option<Outliers>,
+    object_type: &str,
+    object_stats: &ObjectStats,
+) -> Vec<String> {
+    let mut cells = Vec::new();
+    for column in &args.columns {
+        cells.push(match column {
+            Column::ByteRate => match object_stats.byte_rate(stats.elapsed) {
+                Some(byte_rate) => format!("{byte_rate:.2}"),
+                None => "-".to_string(),
+            },
+        });
+    }
+    if let (true, Some(rate)) = (args.with_ci, args.sample_rate) {
+        let (low, high) = sample::confidence_interval(object_stats.count, rate);
+        cells.push(format!("{low}-{high}"));
+    }
+    if let Some(bool_split) = &stats.bool_split {
+        let counts = bool_split.counts(object_type);
+        cells.extend([counts.true_count, counts.false_count, counts.unknown].map(|count| count.to_string()));
+    }
+    if let Some(unique_lines) = &stats.unique_lines {
+        cells.push(unique_lines.count(object_type).to_string());
+    }
+    if args.content_hash {
+        cells.push(format!("{:016x}", object_stats.content_hash));
+    }
+    if !args.severity_rules.is_empty() {
+        cells.push(severity::classify(&args.severity_rules, object_type).unwrap_or("-").to_string());
+    }
+    if let Some(outliers) = outliers {
+        cells.push(if outliers.is_outlier(object_stats.count) { "yes" } else { "-" }.to_string());
+    }
+    if let Some(trend) = &stats.trend {
+        cells.push(trend.label(object_type, object_stats.count));
+    }
+    cells
+}
+
+/// Space separated columns sized to their content: the first one aligned to the left, the numbers to the right.
+fn aligned(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths: Vec<_> = (0..columns)
+        .map(|index| rows.iter().filter_map(|row| row.get(index)).map(|cell| cell.chars().count()).max())
+        .map(Option::unwrap_or_default)
+        .collect();
+    let mut output = String::new();
+    for row in rows {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(index, (cell, width))| {
+                let padding = " ".repeat(width - cell.chars().count());
+                if index == 0 {
+                    format!("{cell}{padding}")
+                } else {
+                    format!("{padding}{cell}")
+                }
+            })
+            .collect();
+        output.push_str(&cells.join("  "));
+        output.push('\n');
+    }
+    output
+}
+
+/// The type as shown in the table, wrapped or truncated to `--wrap-keys`/`--truncate-keys` characters.
+fn type_label(object_type: &str, args: &Args) -> String {
+    if let Some(width) = args.wrap_keys {
+        let chars: Vec<_> = object_type.chars().collect();
+        return chars
+            .chunks(width.get())
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    match args.truncate_keys {
+        Some(width) if object_type.chars().count() > width.get() => {
+            // The ellipsis takes the place of the last character, so the label is exactly `width` wide.
+            let mut truncated: String = object_type.chars().take(width.get() - 1).collect();
+            truncated.push('…');
+            truncated
+        }
+        _ => object_type.to_string(),
+    }
+}
+
+fn histogram_table(histogram: &Histogram) -> anyhow::Result<String> {
+    use cli_table::{format::Justify, Cell, Table};
+    let rows: Vec<_> = histogram
+        .buckets()
+        .into_iter()
+        .map(|(range, count)| vec![range.cell().justify(Justify::Right), count.cell().justify(Justify::Right)])
+        .collect();
+    let table = rows
+        .table()
+        .title(vec![histogram.field.as_str().cell().bold(true), "Count".cell().bold(true)]);
+    let display = table.display().context("Failed to render histogram table")?;
+    Ok(format!("{display}\n"))
+}
+
+fn file_dates_table(file_dates: &FileDates) -> anyhow::Result<String> {
+    use cli_table::{format::Justify, Cell, Table};
+    let rows: Vec<_> = file_dates
+        .sorted()
+        .into_iter()
+        .map(|(date, object_type, count)| {
+            vec![date.cell(), object_type.cell().justify(Justify::Right), count.cell().justify(Justify::Right)]
+        })
+        .collect();
+    let table = rows
+        .table()
+        .title(vec!["Date".cell().bold(true), "Type".cell().bold(true), "Count".cell().bold(true)]);
+    let display = table.display().context("Failed to render file dates table")?;
+    Ok(format!("{display}\n"))
+}
+
+fn heavy_hitters_table(heavy_hitters: &HeavyHitters) -> anyhow::Result<String> {
+    use cli_table::{format::Justify, Cell, Table};
+    let rows: Vec<_> = heavy_hitters
+        .top()
+        .into_iter()
+        .map(|(object_type, estimate)| {
+            vec![
+                object_type.cell().justify(Justify::Right),
+                estimate.count.cell().justify(Justify::Right),
+                estimate.error.cell().justify(Justify::Right),
+            ]
+        })
+        .collect();
+    let table = rows.table().title(vec![
+        "Type".cell().bold(true),
+        "Estimated Count".cell().bold(true),
+        "Max Error".cell().bold(true),
+    ]);
+    let display = table.display().context("Failed to render heavy hitters table")?;
+    Ok(format!("{display}\n"))
+}
+
+// The spec can be pasted as is into the Vega editor to get a bar chart of the counts per type.
+fn vega(stats: &LogStats, args: &Args) -> anyhow::Result<String> {
+    let values: Vec<_> = stats
+        .sorted_entries()
+        .into_iter()
+        .map(|(object_type, object_stats)| serde_json::json!({"category": object_type, "count": object_stats.count}))
+        .collect();
+    let mut spec = serde_json::json!({
+        "$schema": VEGA_LITE_SCHEMA,
+        "data": {"values": values},
+        "mark": "bar",
+        "encoding": {
+            "x": {"field": "category", "type": "nominal", "sort": "-y"},
+            "y": {"field": "count", "type": "quantitative"},
+        },
+    });
+    if args.color_by_type {
+        // An explicit scale maps each type to its color, and unlike using the colors as values it keeps the legend.
+        let (domain, range): (Vec<_>, Vec<_>) = stats
+            .sorted_entries()
+            .into_iter()
+            .map(|(object_type, _)| (object_type, color::ansi256_to_hex(color::type_color(object_type))))
+            .unzip();
+        spec["encoding"]["color"] =
+            serde_json::json!({"field": "category", "type": "nominal", "scale": {"domain": domain, "range": range}});
+    }
+    let mut output = serde_json::to_string_pretty(&spec).context("Failed to serialize Vega-Lite spec")?;
+    output.push('\n');
+    Ok(output)
+}
+
+fn json(stats: &LogStats, args: &Args) -> anyhow::Result<String> {
+    // Sorted by type so the output of two runs can be diffed.
+    let outliers = outliers(stats, args);
+    let mut types = std::collections::BTreeMap::new();
+    for (object_type, object_stats) in &stats.count_map {
+        let mut value = serde_json::to_value(object_stats).context("Failed to serialize stats")?;
+        if let (true, Some(rate)) = (args.with_ci, args.sample_rate) {
+            let (low, high) = sample::confidence_interval(object_stats.count, rate);
+            value["ci_95"] = serde_json::json!([low, high]);
+        }
+        if let Some(bool_split) = &stats.bool_split {
+            value[bool_split.field.as_str()] = serde_json::to_value(bool_split.counts(object_type))?;
+        }
+        if let Some(unique_lines) = &stats.unique_lines {
+            value["unique"] = unique_lines.count(object_type).into();
+        }
+        if args.content_hash {
+            value["content_hash"] = format!("{:016x}", object_stats.content_hash).into();
+        }
+        if !args.severity_rules.is_empty() {
+            value["severity"] = severity::classify(&args.severity_rules, object_type).into();
+        }
+        if let Some(outliers) = outliers {
+            value["outlier"] = outliers.is_outlier(object_stats.count).into();
+        }
+        if let Some(trend) = &stats.trend {
+            value["trend_percent"] = trend.change(object_type, object_stats.count).into();
+        }
+        types.insert(object_type, value);
+    }
+    let mut output = serde_json::to_string_pretty(&types).context("Failed to serialize stats")?;
+    output.push('\n');
+    Ok(output)
+}
+
+/// Plain text columns sorted by type, with nothing that changes between runs over the same input.
+fn canonical(stats: &LogStats) -> String {
+    let entries: std::collections::BTreeMap<_, _> = stats.count_map.iter().collect();
+    let mut rows = vec![vec!["type".to_string(), "count".to_string(), "bytes".to_string()]];
+    for (object_type, object_stats) in entries {
+        rows.push(vec![object_type.clone(), object_stats.count.to_string(), object_stats.bytes.to_string()]);
+    }
+    aligned(&rows)
+}
+
+/// `type=count` lines sorted by type, in the `.properties` format of Java.
+fn properties(stats: &LogStats) -> String {
+    let entries: std::collections::BTreeMap<_, _> = stats.count_map.iter().collect();
+    entries
+        .into_iter()
+        .map(|(object_type, object_stats)| format!("{}={}\n", escape_property_key(object_type), object_stats.count))
+        .collect()
+}
+
+/// Escapes the characters that would end or alter a key. Non-ASCII characters are escaped as `\uXXXX`, because the
+/// properties files are read as ISO 8859-1 by default.
+fn escape_property_key(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '\\' | '=' | ':' | ' ' | '#' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\x0c' => escaped.push_str("\\f"),
+            ' '..='~' => escaped.push(c),
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+/// `WC_<TYPE>=count` lines sorted by type. Types that end up with the same variable name get a `_2`, `_3`... suffix
+/// in type order, so the names are stable between runs over the same types.
+fn env(stats: &LogStats) -> String {
+    let entries: std::collections::BTreeMap<_, _> = stats.count_map.iter().collect();
+    let mut taken = HashSet::new();
+    let mut output = String::new();
+    for (object_type, object_stats) in entries {
+        let base = env_variable(object_type);
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while !taken.insert(name.clone()) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        output.push_str(&format!("{name}={}\n", object_stats.count));
+    }
+    output
+}
+
+/// Upper cases the type and replaces anything that isn't valid in a shell identifier with `_`.
+fn env_variable(object_type: &str) -> String {
+    let sanitized: String = object_type
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+    format!("WC_{sanitized}")
+}
+
+// Timestamp of the points, the line protocol defaults to nanosecond precision.
+fn unix_nanos() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+/// One InfluxDB line protocol point per type, with the type as a tag named after the key and the counters as integer
+/// fields.
+fn influx(stats: &LogStats, args: &Args, timestamp_nanos: u128) -> String {
+    let measurement = escape_influx(&args.measurement, &[',', ' ']);
+    let tag_key = escape_influx(&args.key_name(), &[',', '=', ' ']);
+    let mut output = String::new();
+    for (object_type, object_stats) in stats.sorted_entries() {
+        // Influx rejects empty tag values.
+        if object_type.is_empty() {
+            continue;
+        }
+        let tag_value = escape_influx(object_type, &[',', '=', ' ']);
+        output.push_str(&format!(
+            "{measurement},{tag_key}={tag_value} count={}i,bytes={}i {timestamp_nanos}\n",
+            object_stats.count, object_stats.bytes
+        ));
+    }
+    output
+}
+
+fn escape_influx(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::Parser;
+
+    use super::*;
+
+    // Same counts as `test_data/small.log`.
+    fn sample_stats() -> LogStats {
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+        LogStats { count_map, ..Default::default() }
+    }
+
+    #[test]
+    fn own_table_modes_reject_format() {
+        let parse = |args: &[&str], format: &[&str]| {
+            Args::try_parse_from(["word-counter"].iter().chain(args).chain(format)).map(|_| ())
+        };
+        let histogram = ["--histogram", "latency_ms", "--bins", "10"];
+        assert!(parse(&histogram, &[]).is_ok());
+        assert!(parse(&histogram, &["--format", "json"]).is_err());
+        assert!(parse(&["--by-file-date", "--cumulative"], &["--format", "snapshot"]).is_err());
+        assert!(parse(&["--heavy-hitters", "5"], &["--format", "influx"]).is_err());
+    }
+
+    #[test]
+    fn long_types_wrapped_or_truncated() {
+        let object_type = "https://example.com/a/b";
+        let args = Args::parse_from(["word-counter", "--wrap-keys", "10"]);
+        assert_eq!("https://ex\nample.com/\na/b", type_label(object_type, &args));
+        let args = Args::parse_from(["word-counter", "--truncate-keys", "10"]);
+        assert_eq!("https://e…", type_label(object_type, &args));
+        assert_eq!("short", type_label("short", &args));
+
+        let mut stats = LogStats::default();
+        stats.count_map.insert(object_type.to_string(), ObjectStats { count: 1, bytes: 1, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--wrap-keys", "10"]);
+        let table = table(&stats, &args).unwrap();
+        assert!(table.contains("https://ex"));
+        assert!(!table.contains(object_type));
+    }
+
+    #[test]
+    fn canonical_is_deterministic() {
+        let args = Args::parse_from(["word-counter", "--format", "canonical"]);
+        let render_small_log = || {
+            let mut stats = LogStats::default();
+            crate::process_file("test_data/small.log", &mut stats, &args).unwrap();
+            render(&stats, &args).unwrap()
+        };
+        let output = render_small_log();
+        assert_eq!(output, render_small_log());
+        assert_eq!("type  count  bytes\nA         3     76\nB         4    169\n", output);
+    }
+
+    #[test]
+    fn plain_has_no_borders() {
+        let mut stats = sample_stats();
+        stats.count_map.insert("Longer".to_string(), ObjectStats { count: 10, bytes: 1024, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--format", "plain", "--severity-rule", "warn:B"]);
+        let expected = "\
+Type    Count  Size Bytes  Severity
+Longer     10        1024         -
+B           4         169      warn
+A           3          76         -
+";
+        assert_eq!(expected, render(&stats, &args).unwrap());
+    }
+
+    #[test]
+    fn bool_split_columns() {
+        let mut stats = LogStats::default();
+        let mut bool_split = crate::bool_split::BoolSplit::new("ok");
+        for (object_type, line) in [("A", serde_json::json!({"ok": true})), ("A", serde_json::json!({"ok": 1}))] {
+            bool_split.record(object_type, &line);
+            stats.record(object_type, "", 1);
+        }
+        stats.bool_split = Some(bool_split);
+        let args = Args::parse_from(["word-counter", "--format", "plain", "--split-bool", "ok"]);
+        let expected = "Type  Count  Size Bytes  True  False  Unknown\nA         2           2     1      0        1\n";
+        assert_eq!(expected, render(&stats, &args).unwrap());
+    }
+
+    #[test]
+    fn properties_keys_are_escaped() {
+        let mut stats = sample_stats();
+        stats.count_map.insert("a=b: c".to_string(), ObjectStats { count: 2, bytes: 10, content_hash: 0 });
+        stats.count_map.insert("né".to_string(), ObjectStats { count: 1, bytes: 10, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--format", "properties"]);
+        let expected = "A=3\nB=4\na\\=b\\:\\ c=2\nn\\u00e9=1\n";
+        assert_eq!(expected, render(&stats, &args).unwrap());
+    }
+
+    #[test]
+    fn env_names_are_valid_identifiers() {
+        let mut stats = LogStats::default();
+        stats.count_map.insert("user-login.ok".to_string(), ObjectStats { count: 2, bytes: 10, content_hash: 0 });
+        stats.count_map.insert("user_login ok".to_string(), ObjectStats { count: 5, bytes: 10, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--format", "env"]);
+        let expected = "WC_USER_LOGIN_OK=2\nWC_USER_LOGIN_OK_2=5\n";
+        assert_eq!(expected, render(&stats, &args).unwrap());
+    }
+
+    #[test]
+    fn markdown_escapes_pipes() {
+        let mut stats = sample_stats();
+        stats.count_map.insert("a|b".to_string(), ObjectStats { count: 1, bytes: 10, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--format", "markdown"]);
+        let expected = "| Type | Count | Size Bytes |\n|---|---|---|\n| B | 4 | 169 |\n| A | 3 | 76 |\n\
+                        | a\\|b | 1 | 10 |\n";
+        assert_eq!(expected, render(&stats, &args).unwrap());
+    }
+
+    #[test]
+    fn vega_spec_embeds_counts() {
+        let stats = sample_stats();
+        let args = Args::parse_from(["word-counter"]);
+        let spec: serde_json::Value = serde_json::from_str(&vega(&stats, &args).unwrap()).unwrap();
+        let expected = serde_json::json!([{"category": "B", "count": 4}, {"category": "A", "count": 3}]);
+        assert_eq!(expected, spec["data"]["values"]);
+        assert_eq!("bar", spec["mark"]);
+        assert!(spec["encoding"]["color"].is_null());
+
+        let args = Args::parse_from(["word-counter", "--color-by-type"]);
+        let spec: serde_json::Value = serde_json::from_str(&vega(&stats, &args).unwrap()).unwrap();
+        let color = &spec["encoding"]["color"];
+        assert_eq!("category", color["field"]);
+        assert!(color.get("legend").is_none());
+        assert_eq!(serde_json::json!(["B", "A"]), color["scale"]["domain"]);
+        let range: Vec<_> = ["B", "A"].map(|object_type| color::ansi256_to_hex(color::type_color(object_type))).into();
+        assert_eq!(serde_json::json!(range), color["scale"]["range"]);
+    }
+
+    #[test]
+    fn json_keyed_by_type() {
+        let args = Args::parse_from(["word-counter"]);
+        let output: serde_json::Value = serde_json::from_str(&json(&sample_stats(), &args).unwrap()).unwrap();
+        assert_eq!(serde_json::json!({"A": {"count": 3, "bytes": 76}, "B": {"count": 4, "bytes": 169}}), output);
+
+        let args = Args::parse_from(["word-counter", "--severity-rule", "warn:B"]);
+        let output: serde_json::Value = serde_json::from_str(&json(&sample_stats(), &args).unwrap()).unwrap();
+        assert_eq!("warn", output["B"]["severity"]);
+        assert!(output["A"]["severity"].is_null());
+    }
+
+    #[test]
+    fn influx_line_protocol() {
+        let mut stats = sample_stats();
+        stats.count_map.insert("GET /a b,c=d".to_string(), ObjectStats { count: 1, bytes: 10, content_hash: 0 });
+        let args = Args::parse_from(["word-counter", "--format", "influx", "--measurement", "log counts"]);
+        let output = influx(&stats, &args, 1_700_000_000_000_000_000);
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("log\\ counts,type=B count=4i,bytes=169i 1700000000000000000", lines[0]);
+        // Parse the point back: measurement+tags, fields and timestamp are separated by unescaped spaces.
+        let point = lines[2];
+        let (series, rest) = point.split_at(point.find(" count=").unwrap());
+        assert_eq!(r"log\ counts,type=GET\ /a\ b\,c\=d", series);
+        let field_set = rest.split_whitespace().next().unwrap();
+        let fields: HashMap<_, _> = field_set.split(',').map(|field| field.split_once('=').unwrap()).collect();
+        assert_eq!("1i", fields["count"]);
+        assert_eq!("10i", fields["bytes"]);
+    }
+
+    #[test]
+    fn influx_tag_named_after_the_key_used() {
+        let tag = |extra: &[&str]| {
+            let args = Args::parse_from(["word-counter", "--format", "influx"].iter().chain(extra));
+            let output = influx(&sample_stats(), &args, 0);
+            let series = output.lines().next().unwrap().split(' ').next().unwrap().to_string();
+            series.split_once(',').unwrap().1.split_once('=').unwrap().0.to_string()
+        };
+        assert_eq!("type", tag(&[]));
+        assert_eq!("kind", tag(&["--key", "kind"]));
+        assert_eq!("method_status", tag(&["--key-join", "method,status"]));
+        assert_eq!("level", tag(&["--levels"]));
+        assert_eq!("severity", tag(&["--levels=severity"]));
+        assert_eq!("message", tag(&["--token-field", "message:0"]));
+        assert_eq!("time", tag(&["--time-prefix", "time:13"]));
+    }
+}
@@ -0,0 +1,198 @@
+//! Progress and ETA of the input being processed, reported on stderr.
+
+use std::io::{IsTerminal, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How many lines are processed between two checks of the clock.
+const CHECK_INTERVAL_LINES: usize = 4096;
+const REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Counts the bytes read from the inner reader.
+///
+/// For compressed inputs it wraps the file *before* the decoder, so the count is the position in the compressed file,
+/// which is the only size we know upfront.
+pub struct CountingReader<R> {
+    inner: R,
+    consumed: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R, consumed: Arc<AtomicU64>) -> Self {
+        Self { inner, consumed }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let num_bytes = self.inner.read(buf)?;
+        self.consumed.fetch_add(num_bytes as u64, Ordering::Relaxed);
+        Ok(num_bytes)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.consumed.store(position, Ordering::Relaxed);
+        Ok(position)
+    }
+}
+
+pub struct Progress {
+    total_bytes: u64,
+    consumed: Arc<AtomicU64>,
+    start: Instant,
+    last_report: Instant,
+    lines_since_check: usize,
+}
+
+impl Progress {
+    pub fn new(total_bytes: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            total_bytes,
+            consumed: Default::default(),
+            start: now,
+            last_report: now,
+            lines_since_check: 0,
+        }
+    }
+
+    /// Counter to be shared with the `CountingReader` of the input.
+    pub fn counter(&self) -> Arc<AtomicU64> {
+        self.consumed.clone()
+    }
+
+    /// Fraction of the input consumed so far, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        (self.consumed.load(Ordering::Relaxed) as f64 / self.total_bytes as f64).min(1.0)
+    }
+
+    /// Accounts for a processed line, reporting the progress every `REPORT_INTERVAL`.
+    pub fn tick(&mut self) {
+        self.lines_since_check += 1;
+        if self.lines_since_check < CHECK_INTERVAL_LINES {
+            return;
+        }
+        self.lines_since_check = 0;
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            self.report();
+        }
+    }
+
+    pub fn finish(&self) {
+        self.report();
+        eprintln!();
+    }
+
+    fn report(&self) {
+        let fraction = self.fraction();
+        // The ETA assumes the rest of the input is processed at the same pace than what we've seen so far.
+        let eta = match fraction > 0.0 {
+            true => format!("{:.0}s", self.start.elapsed().as_secs_f64() * (1.0 - fraction) / fraction),
+            false => "?".to_string(),
+        };
+        eprint!("\r{:5.1}% ETA {eta}  ", fraction * 100.0);
+    }
+}
+
+/// Whether the `--title-progress` is shown. Only terminals understand the escape sequence, anywhere else, like a file
+/// stderr is redirected to, it would be garbage.
+pub fn title_progress_enabled(title_progress: bool, force_tty: bool) -> bool {
+    title_progress && (force_tty || std::io::stderr().is_terminal())
+}
+
+/// Lines read so far, shown in the terminal title so it doesn't get in the way of anything written to the terminal.
+pub struct TitleProgress<W> {
+    writer: W,
+    last_report: Instant,
+    lines_since_check: usize,
+}
+
+impl<W: Write> TitleProgress<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            last_report: Instant::now(),
+            lines_since_check: 0,
+        }
+    }
+
+    /// Accounts for a processed line, updating the title every `REPORT_INTERVAL`.
+    pub fn tick(&mut self, lines_read: usize) {
+        self.lines_since_check += 1;
+        if self.lines_since_check < CHECK_INTERVAL_LINES {
+            return;
+        }
+        self.lines_since_check = 0;
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            self.report(lines_read);
+        }
+    }
+
+    pub fn finish(&mut self, lines_read: usize) {
+        self.report(lines_read);
+    }
+
+    fn report(&mut self, lines_read: usize) {
+        // The title is only informative, failing to update it isn't worth stopping the count.
+        let _ = write!(self.writer, "\x1b]0;word-counter: {lines_read} lines\x07");
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    #[test]
+    fn gzip_progress_tracks_compressed_bytes() {
+        // Pseudo random payloads so the compressed file is much larger than the decoder buffers.
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut seed: u64 = 42;
+        for _ in 0..50_000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            writeln!(encoder, "{{\"type\":\"A\",\"id\":\"{seed:x}\"}}").unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+
+        let progress = Progress::new(compressed.len() as u64);
+        let counting_reader = CountingReader::new(compressed.as_slice(), progress.counter());
+        let reader = BufReader::new(GzDecoder::new(counting_reader));
+        let mut fractions = vec![];
+        for (index, line) in reader.lines().enumerate() {
+            line.unwrap();
+            if index % 1000 == 0 {
+                fractions.push(progress.fraction());
+            }
+        }
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(fractions[fractions.len() / 2] > fractions[0]);
+        assert!(fractions[fractions.len() / 2] < 1.0);
+        assert_eq!(1.0, progress.fraction());
+    }
+
+    #[test]
+    fn title_is_set_with_escape_sequence() {
+        assert!(title_progress_enabled(true, true));
+        assert!(!title_progress_enabled(false, true));
+        let mut sut = TitleProgress::new(Vec::new());
+        for lines_read in 1..=3 {
+            sut.tick(lines_read);
+        }
+        sut.finish(3);
+        assert_eq!(b"\x1b]0;word-counter: 3 lines\x07".as_slice(), sut.writer.as_slice());
+    }
+}
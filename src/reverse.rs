@@ -0,0 +1,104 @@
+//! Reading a file from its last line to its first, for `--reverse-read`.
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+/// How many bytes are read at once, going backward from the end of the file.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Source of the lines to count, lets `process_reader` handle forward and backward reading the same way.
+pub trait ReadLine {
+    /// Appends the next line, including its `\n` if it has one, returning its length. 0 means there are no more lines.
+    fn read_line(&mut self, buffer: &mut String) -> io::Result<usize>;
+}
+
+impl<R: BufRead> ReadLine for R {
+    fn read_line(&mut self, buffer: &mut String) -> io::Result<usize> {
+        BufRead::read_line(self, buffer)
+    }
+}
+
+/// Yields the lines of the inner reader in reverse order. The lines are the same as reading forward, byte by byte, so
+/// the counts and sizes don't change, only the order they are counted in.
+pub struct ReverseLines<R> {
+    inner: R,
+    /// Offset where the reading stops, the lines before it are left out.
+    start: u64,
+    /// Offset of the start of `pending` in the file, everything before it hasn't been read yet.
+    position: u64,
+    /// Read bytes that haven't been returned as lines yet.
+    pending: Vec<u8>,
+}
+
+impl<R: Read + Seek> ReverseLines<R> {
+    /// `start` has to be the start of a line, see `checkpoint::seek_to_line`.
+    pub fn new(mut inner: R, start: u64) -> io::Result<Self> {
+        let position = inner.seek(SeekFrom::End(0))?.max(start);
+        Ok(Self {
+            inner,
+            start,
+            position,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Prepends the chunk right before `pending`. Returns false at `start`.
+    fn read_previous_chunk(&mut self) -> io::Result<bool> {
+        if self.position == self.start {
+            return Ok(false);
+        }
+        let chunk_size = (self.position - self.start).min(CHUNK_SIZE);
+        self.position -= chunk_size;
+        self.inner.seek(SeekFrom::Start(self.position))?;
+        let mut chunk = vec![0; chunk_size as usize];
+        self.inner.read_exact(&mut chunk)?;
+        chunk.append(&mut self.pending);
+        self.pending = chunk;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> ReadLine for ReverseLines<R> {
+    fn read_line(&mut self, buffer: &mut String) -> io::Result<usize> {
+        let line_start = loop {
+            // The `\n` at the very end belongs to the last line, the one before it ends the previous line.
+            let search_end = self.pending.len().saturating_sub(1);
+            if let Some(index) = self.pending[..search_end].iter().rposition(|byte| *byte == b'\n') {
+                break index + 1;
+            }
+            if !self.read_previous_chunk()? {
+                break 0;
+            }
+        };
+        let line = self.pending.split_off(line_start);
+        let line = std::str::from_utf8(&line).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        buffer.push_str(line);
+        Ok(line.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn reversed(content: &str) -> Vec<String> {
+        let mut sut = ReverseLines::new(Cursor::new(content.as_bytes().to_vec()), 0).unwrap();
+        let mut lines = Vec::new();
+        let mut buffer = String::new();
+        while sut.read_line(&mut buffer).unwrap() > 0 {
+            lines.push(std::mem::take(&mut buffer));
+        }
+        lines
+    }
+
+    #[test]
+    fn lines_backward() {
+        assert_eq!(vec!["c", "b\n", "a\n"], reversed("a\nb\nc"));
+        assert_eq!(vec!["c\n", "\n", "a\n"], reversed("a\n\nc\n"));
+        assert!(reversed("").is_empty());
+        // Longer than a chunk, so the lines are split across reads.
+        let long_line = "x".repeat(CHUNK_SIZE as usize * 2);
+        assert_eq!(vec!["last\n".to_string(), format!("{long_line}\n")], reversed(&format!("{long_line}\nlast\n")));
+    }
+}
@@ -0,0 +1,95 @@
+//! Random sampling of the lines, to estimate the counts of a huge input from a fraction of it.
+
+use std::collections::HashMap;
+
+use crate::ObjectStats;
+
+/// Parses a sampling rate, the fraction of lines kept, in `(0, 1]`.
+pub fn parse_rate(rate: &str) -> Result<f64, String> {
+    let rate: f64 = rate.parse().map_err(|_| format!("Invalid sample rate {rate}"))?;
+    if !(rate > 0.0 && rate <= 1.0) {
+        return Err(format!("The sample rate must be in (0, 1], got {rate}"));
+    }
+    Ok(rate)
+}
+
+/// Keeps each line with the probability of the rate. The generator is seeded, so the same seed keeps the same lines.
+#[derive(Debug, PartialEq)]
+pub struct Sampler {
+    rate: f64,
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Self { rate, state: seed }
+    }
+
+    pub fn keep(&mut self) -> bool {
+        // SplitMix64, good enough for sampling and it saves a dependency. The top 53 bits make a uniform `[0, 1)`.
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        ((z >> 11) as f64 / (1u64 << 53) as f64) < self.rate
+    }
+}
+
+/// Scales the counts and sizes of the sampled lines up to estimates of the whole input.
+pub fn scale(count_map: &mut HashMap<String, ObjectStats>, rate: f64) {
+    for object_stats in count_map.values_mut() {
+        object_stats.count = (object_stats.count as f64 / rate).round() as usize;
+        object_stats.bytes = (object_stats.bytes as f64 / rate).round() as usize;
+    }
+}
+
+/// z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// Approximate 95% confidence interval of a scaled up count, using the normal approximation to the binomial distribution
+/// of the sampled count. It's bad for the types with very few sampled lines, which is also when it's the widest.
+pub fn confidence_interval(estimate: usize, rate: f64) -> (usize, usize) {
+    // The sampled count is `estimate * rate`, with a variance of `estimate * rate * (1 - rate)`, scaled by `1 / rate`.
+    let margin = Z_95 * (estimate as f64 * (1.0 - rate) / rate).sqrt();
+    let estimate = estimate as f64;
+    ((estimate - margin).max(0.0).round() as usize, (estimate + margin).round() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_keeps_same_lines() {
+        let kept = |seed| {
+            let mut sut = Sampler::new(0.1, seed);
+            (0..10_000).filter(|_| sut.keep()).count()
+        };
+        assert_eq!(kept(7), kept(7));
+        assert!((900..1_100).contains(&kept(7)));
+        let mut sut = Sampler::new(1.0, 7);
+        assert!((0..10_000).all(|_| sut.keep()));
+    }
+
+    #[test]
+    fn interval_widens_with_lower_rates() {
+        let width = |rate| {
+            let (low, high) = confidence_interval(10_000, rate);
+            assert!(low <= 10_000 && 10_000 <= high);
+            high - low
+        };
+        assert_eq!(0, width(1.0));
+        assert!(width(0.5) < width(0.1));
+        assert!(width(0.1) < width(0.01));
+        assert_eq!((383, 1617), confidence_interval(1_000, 0.01));
+    }
+
+    #[test]
+    fn invalid_rates() {
+        assert_eq!(Ok(0.5), parse_rate("0.5"));
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("1.5").is_err());
+        assert!(parse_rate("NaN").is_err());
+    }
+}
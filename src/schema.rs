@@ -0,0 +1,116 @@
+//! Detection of the points where the top-level keys of the lines change, e.g. when a producer is upgraded.
+//!
+//! The lines are grouped in windows of a fixed number of lines, and the schema of a window is the set of keys present in
+//! at least half of its lines, so optional fields don't count as a change. A change is reported when a window's schema
+//! differs from the previous window's, with the line range of both windows, the transition being somewhere within it.
+
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, PartialEq)]
+pub struct SchemaChange {
+    pub from_line: usize,
+    pub to_line: usize,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SchemaChanges {
+    window_lines: usize,
+    window_start: usize,
+    window_end: usize,
+    lines: usize,
+    key_counts: HashMap<String, usize>,
+    /// First line and schema of the last completed window.
+    previous: Option<(usize, BTreeSet<String>)>,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaChanges {
+    pub fn new(window_lines: usize) -> Self {
+        Self {
+            window_lines,
+            window_start: 0,
+            window_end: 0,
+            lines: 0,
+            key_counts: HashMap::new(),
+            previous: None,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Lines that aren't json objects have no keys, they count towards the window as an empty schema.
+    pub fn record(&mut self, line_number: usize, line: &serde_json::Value) {
+        if self.lines == 0 {
+            self.window_start = line_number;
+        }
+        self.window_end = line_number;
+        self.lines += 1;
+        for key in line.as_object().into_iter().flat_map(|object| object.keys()) {
+            match self.key_counts.get_mut(key) {
+                Some(count) => *count += 1,
+                None => {
+                    self.key_counts.insert(key.clone(), 1);
+                }
+            }
+        }
+        if self.lines == self.window_lines {
+            self.close_window();
+        }
+    }
+
+    /// Compares the last, partial, window.
+    pub fn finish(&mut self) {
+        if self.lines > 0 {
+            self.close_window();
+        }
+    }
+
+    fn close_window(&mut self) {
+        let schema: BTreeSet<_> = self
+            .key_counts
+            .drain()
+            .filter(|(_, count)| count * 2 >= self.lines)
+            .map(|(key, _)| key)
+            .collect();
+        if let Some((from_line, previous)) = &self.previous {
+            if *previous != schema {
+                self.changes.push(SchemaChange {
+                    from_line: *from_line,
+                    to_line: self.window_end,
+                    added: schema.difference(previous).cloned().collect(),
+                    removed: previous.difference(&schema).cloned().collect(),
+                });
+            }
+        }
+        self.previous = Some((self.window_start, schema));
+        self.lines = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_field_is_reported_near_the_boundary() {
+        let mut sut = SchemaChanges::new(10);
+        for line_number in 1..=100 {
+            let line = match line_number {
+                // An optional field in a few lines isn't a change.
+                7 | 23 => serde_json::json!({"type": "A", "debug": true}),
+                ..=54 => serde_json::json!({"type": "A"}),
+                _ => serde_json::json!({"type": "A", "version": 2}),
+            };
+            sut.record(line_number, &line);
+        }
+        sut.finish();
+        let expected = vec![SchemaChange {
+            from_line: 41,
+            to_line: 60,
+            added: vec!["version".to_string()],
+            removed: vec![],
+        }];
+        assert_eq!(expected, sut.changes);
+    }
+}
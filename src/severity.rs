@@ -0,0 +1,162 @@
+//! Severity of each type, inferred from its name with user supplied rules.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use regex::Regex;
+
+use crate::ObjectStats;
+
+#[derive(Debug, Clone)]
+pub struct SeverityRule {
+    pub severity: String,
+    /// Has to match the whole type.
+    pub pattern: Regex,
+}
+
+/// Parses a `<severity>:<regex>` rule, e.g. `error:.*(error|fail).*`.
+pub fn parse_rule(rule: &str) -> Result<SeverityRule, String> {
+    let (severity, pattern) = rule
+        .split_once(':')
+        .ok_or_else(|| format!("Expected <severity>:<regex>, got {rule}"))?;
+    if severity.is_empty() {
+        return Err(format!("Missing severity in {rule}"));
+    }
+    let pattern = Regex::new(&format!("^(?:{pattern})$")).map_err(|error| error.to_string())?;
+    Ok(SeverityRule {
+        severity: severity.to_string(),
+        pattern,
+    })
+}
+
+/// Severity of the first rule matching the type, in the order the rules were given.
+pub fn classify<'a>(rules: &'a [SeverityRule], object_type: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.pattern.is_match(object_type))
+        .map(|rule| rule.severity.as_str())
+}
+
+/// Maximum count allowed for the types of a severity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeverityThreshold {
+    pub severity: String,
+    pub max: usize,
+}
+
+/// Parses a `<severity>:<max count>` threshold, e.g. `error:0`.
+pub fn parse_threshold(threshold: &str) -> Result<SeverityThreshold, String> {
+    let (severity, max) = threshold
+        .split_once(':')
+        .ok_or_else(|| format!("Expected <severity>:<max count>, got {threshold}"))?;
+    if severity.is_empty() {
+        return Err(format!("Missing severity in {threshold}"));
+    }
+    let max = max.parse().map_err(|_| format!("Invalid max count in {threshold}"))?;
+    Ok(SeverityThreshold {
+        severity: severity.to_string(),
+        max,
+    })
+}
+
+/// A severity whose types add up to more lines than its threshold allows.
+#[derive(Debug, PartialEq)]
+pub struct Breach<'a> {
+    pub severity: &'a str,
+    pub total: usize,
+    pub max: usize,
+    /// Types of the severity, by descending count.
+    pub types: Vec<(&'a str, usize)>,
+}
+
+impl fmt::Display for Breach<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} lines, over the threshold of {} (", self.severity, self.total, self.max)?;
+        for (index, (object_type, count)) in self.types.iter().enumerate() {
+            let separator = if index == 0 { "" } else { ", " };
+            write!(f, "{separator}{object_type}: {count}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Severities over their threshold, in the order the thresholds were given.
+pub fn breaches<'a>(
+    rules: &[SeverityRule],
+    thresholds: &'a [SeverityThreshold],
+    count_map: &'a HashMap<String, ObjectStats>,
+) -> Vec<Breach<'a>> {
+    let mut breaches = Vec::new();
+    for threshold in thresholds {
+        let mut types: Vec<_> = count_map
+            .iter()
+            .filter(|(object_type, _)| classify(rules, object_type) == Some(threshold.severity.as_str()))
+            .map(|(object_type, object_stats)| (object_type.as_str(), object_stats.count))
+            .collect();
+        let total = types.iter().map(|(_, count)| count).sum();
+        if total > threshold.max {
+            types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            breaches.push(Breach {
+                severity: &threshold.severity,
+                total,
+                max: threshold.max,
+                types,
+            });
+        }
+    }
+    breaches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules: Vec<_> = ["error:.*(error|fail).*", "warn:.*(retry|slow).*", "info:.*"]
+            .into_iter()
+            .map(|rule| parse_rule(rule).unwrap())
+            .collect();
+        assert_eq!(Some("error"), classify(&rules, "payment_failed"));
+        assert_eq!(Some("error"), classify(&rules, "error_retry"));
+        assert_eq!(Some("warn"), classify(&rules, "slow_query"));
+        assert_eq!(Some("info"), classify(&rules, "login"));
+        assert_eq!(None, classify(&rules[..2], "login"));
+    }
+
+    #[test]
+    fn pattern_matches_whole_type() {
+        let rules = vec![parse_rule("error:error").unwrap()];
+        assert_eq!(None, classify(&rules, "no_error_here"));
+        assert_eq!(Some("error"), classify(&rules, "error"));
+        assert!(parse_rule("no-separator").is_err());
+        assert!(parse_rule("error:(").is_err());
+    }
+
+    #[test]
+    fn threshold_breached_per_severity() {
+        let rules: Vec<_> = ["error:.*fail.*", "warn:.*slow.*"]
+            .into_iter()
+            .map(|rule| parse_rule(rule).unwrap())
+            .collect();
+        let mut count_map = HashMap::new();
+        count_map.insert("payment_failed".to_string(), ObjectStats { count: 2, bytes: 20, content_hash: 0 });
+        count_map.insert("login_failed".to_string(), ObjectStats { count: 1, bytes: 10, content_hash: 0 });
+        count_map.insert("slow_query".to_string(), ObjectStats { count: 50, bytes: 500, content_hash: 0 });
+        count_map.insert("login".to_string(), ObjectStats { count: 500, bytes: 5000, content_hash: 0 });
+        let thresholds: Vec<_> = ["error:0", "warn:100"]
+            .into_iter()
+            .map(|threshold| parse_threshold(threshold).unwrap())
+            .collect();
+        let expected = vec![Breach {
+            severity: "error",
+            total: 3,
+            max: 0,
+            types: vec![("payment_failed", 2), ("login_failed", 1)],
+        }];
+        assert_eq!(expected, breaches(&rules, &thresholds, &count_map));
+        let message = "error: 3 lines, over the threshold of 0 (payment_failed: 2, login_failed: 1)";
+        assert_eq!(message, expected[0].to_string());
+        assert!(parse_threshold("error:-1").is_err());
+    }
+}
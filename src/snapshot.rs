@@ -0,0 +1,47 @@
+//! Compact, single line encoding of the counts: MessagePack, gzipped and base64 encoded.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::ObjectStats;
+
+pub fn encode(count_map: &HashMap<String, ObjectStats>) -> anyhow::Result<String> {
+    // Sorted so the same counts always give the same blob.
+    let sorted: BTreeMap<_, _> = count_map.iter().collect();
+    let packed = rmp_serde::to_vec(&sorted).context("Failed to serialize snapshot")?;
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&packed).context("Failed to compress snapshot")?;
+    let compressed = encoder.finish().context("Failed to compress snapshot")?;
+    Ok(BASE64.encode(compressed))
+}
+
+pub fn decode(blob: &str) -> anyhow::Result<HashMap<String, ObjectStats>> {
+    let compressed = BASE64.decode(blob.trim()).context("The snapshot isn't valid base64")?;
+    let mut packed = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut packed)
+        .context("Failed to decompress snapshot")?;
+    rmp_serde::from_slice(&packed).context("Failed to deserialize snapshot")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut count_map = HashMap::new();
+        count_map.insert("A".to_string(), ObjectStats { count: 3, bytes: 76, content_hash: 0 });
+        count_map.insert("B".to_string(), ObjectStats { count: 4, bytes: 169, content_hash: 0 });
+        let blob = encode(&count_map).unwrap();
+        assert!(!blob.contains('\n'));
+        assert_eq!(count_map, decode(&blob).unwrap());
+        assert!(decode("not a snapshot").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Keys taken from a part of a text field rather than a whole field, for `--token-field` and `--time-prefix`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenField {
+    pub field: String,
+    /// Position of the token among the whitespace separated words of the field, starting at 0.
+    pub index: usize,
+}
+
+impl TokenField {
+    /// The token of the line, `None` if the field is missing, isn't a string or has fewer words.
+    pub fn extract<'a>(&self, line: &'a serde_json::Value) -> Option<&'a str> {
+        line.get(&self.field)?.as_str()?.split_whitespace().nth(self.index)
+    }
+}
+
+/// Parses a `<field>:<index>` token field, e.g. `message:0`.
+pub fn parse_token_field(token_field: &str) -> Result<TokenField, String> {
+    let (field, index) = token_field
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Expected <field>:<index>, got {token_field}"))?;
+    if field.is_empty() {
+        return Err(format!("Missing field in {token_field}"));
+    }
+    let index = index.parse().map_err(|_| format!("Invalid index in {token_field}"))?;
+    Ok(TokenField {
+        field: field.to_string(),
+        index,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimePrefix {
+    pub field: String,
+    /// Characters of the timestamp kept, e.g. 13 for the hour of an RFC 3339 timestamp.
+    pub len: usize,
+}
+
+impl TimePrefix {
+    /// The first `len` characters of the field, `None` if it's missing, isn't a string or is shorter. The timestamp
+    /// isn't parsed, so it's only as good as the timestamps being formatted the same way in all the lines.
+    pub fn extract<'a>(&self, line: &'a serde_json::Value) -> Option<&'a str> {
+        let timestamp = line.get(&self.field)?.as_str()?;
+        match timestamp.char_indices().nth(self.len) {
+            Some((end, _)) => Some(&timestamp[..end]),
+            None if timestamp.chars().count() == self.len => Some(timestamp),
+            None => None,
+        }
+    }
+}
+
+/// Parses a `<field>:<len>` time prefix, e.g. `time:13`.
+pub fn parse_time_prefix(time_prefix: &str) -> Result<TimePrefix, String> {
+    let (field, len) = time_prefix
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Expected <field>:<len>, got {time_prefix}"))?;
+    if field.is_empty() {
+        return Err(format!("Missing field in {time_prefix}"));
+    }
+    let len = match len.parse() {
+        Ok(len) if len > 0 => len,
+        _ => return Err(format!("Invalid length in {time_prefix}")),
+    };
+    Ok(TimePrefix {
+        field: field.to_string(),
+        len,
+    })
+}
@@ -0,0 +1,88 @@
+//! Counts of consecutive pairs of types, treating the input as an ordered stream of events.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Transitions {
+    /// Whether a skipped line breaks the sequence, instead of being ignored.
+    reset_on_skip: bool,
+    previous: Option<String>,
+    /// Nested maps so an existing transition can be looked up with the borrowed types, without allocating.
+    counts: HashMap<String, HashMap<String, usize>>,
+}
+
+impl Transitions {
+    pub fn new(reset_on_skip: bool) -> Self {
+        Self {
+            reset_on_skip,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, object_type: &str) {
+        if let Some(previous) = &self.previous {
+            let to_counts = match self.counts.get_mut(previous.as_str()) {
+                Some(to_counts) => to_counts,
+                None => self.counts.entry(previous.clone()).or_default(),
+            };
+            match to_counts.get_mut(object_type) {
+                Some(count) => *count += 1,
+                None => {
+                    to_counts.insert(object_type.to_string(), 1);
+                }
+            }
+        }
+        // Reusing the previous `String` avoids an allocation per line.
+        let previous = self.previous.get_or_insert_with(String::new);
+        previous.clear();
+        previous.push_str(object_type);
+    }
+
+    /// The last line of an input and the first of the next one aren't consecutive events.
+    pub fn start_input(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn skip(&mut self) {
+        if self.reset_on_skip {
+            self.previous = None;
+        }
+    }
+
+    /// `(from, to, count)` sorted by descending count, then by types.
+    pub fn sorted(&self) -> Vec<(&str, &str, usize)> {
+        let mut transitions: Vec<_> = self
+            .counts
+            .iter()
+            .flat_map(|(from, to_counts)| to_counts.iter().map(move |(to, count)| (from.as_str(), to.as_str(), *count)))
+            .collect();
+        transitions.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (a.0, a.1).cmp(&(b.0, b.1))));
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_pairs_are_counted() {
+        let mut sut = Transitions::new(false);
+        for object_type in ["A", "B", "A", "B", "B"] {
+            sut.record(object_type);
+        }
+        sut.skip();
+        sut.record("A");
+        assert_eq!(vec![("A", "B", 2), ("B", "A", 2), ("B", "B", 1)], sut.sorted());
+    }
+
+    #[test]
+    fn skipped_lines_reset_the_sequence() {
+        let mut sut = Transitions::new(true);
+        sut.record("A");
+        sut.skip();
+        sut.record("B");
+        sut.record("C");
+        assert_eq!(vec![("B", "C", 1)], sut.sorted());
+    }
+}
@@ -0,0 +1,52 @@
+//! Per-type counts of the distinct lines, where only the first occurrence of each line is counted.
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct UniqueLines {
+    /// Hashes of the lines seen so far. Two different lines with the same 64 bits hash would be counted once, which is
+    /// unlikely enough to keep a hash instead of the whole line.
+    seen: HashSet<u64>,
+    counts: HashMap<String, usize>,
+}
+
+impl UniqueLines {
+    pub fn record(&mut self, object_type: &str, line: &str) {
+        if !self.seen.insert(crate::line_hash(line)) {
+            return;
+        }
+        match self.counts.get_mut(object_type) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(object_type.to_string(), 1);
+            }
+        }
+    }
+
+    pub fn count(&self, object_type: &str) -> usize {
+        self.counts.get(object_type).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_lines_are_counted_once() {
+        let mut sut = UniqueLines::default();
+        let lines = [
+            ("A", "{\"type\":\"A\",\"id\":1}\n"),
+            ("A", "{\"type\":\"A\",\"id\":1}\n"),
+            ("A", "{\"type\":\"A\",\"id\":2}\n"),
+            ("A", "{\"type\":\"A\",\"id\":1}"),
+            ("B", "{\"type\":\"B\"}\n"),
+        ];
+        for (object_type, line) in lines {
+            sut.record(object_type, line);
+        }
+        assert_eq!(2, sut.count("A"));
+        assert_eq!(1, sut.count("B"));
+        assert_eq!(0, sut.count("C"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Strict UTF-8 validation of the lines, reporting where the first invalid byte is, for `--strict-utf8`.
+
+use std::io::{self, BufRead};
+
+use crate::reverse::ReadLine;
+
+/// Bytes shown before and after an invalid one, to find it in the input.
+const CONTEXT_BYTES: usize = 16;
+
+/// Reads the lines as bytes and validates them, keeping track of their offset in the input.
+pub struct Utf8Check<R> {
+    inner: R,
+    /// Offset of the next line in the input.
+    offset: u64,
+    continue_on_invalid: bool,
+    bytes: Vec<u8>,
+}
+
+impl<R: BufRead> Utf8Check<R> {
+    /// `offset` is where the reader is in the input, so the reported offsets are offsets of the whole input.
+    pub fn new(inner: R, offset: u64, continue_on_invalid: bool) -> Self {
+        Self {
+            inner,
+            offset,
+            continue_on_invalid,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> ReadLine for Utf8Check<R> {
+    /// Fails on invalid lines, or warns and replaces their invalid sequences with `U+FFFD` if `continue_on_invalid`.
+    fn read_line(&mut self, buffer: &mut String) -> io::Result<usize> {
+        self.bytes.clear();
+        let num_bytes = self.inner.read_until(b'\n', &mut self.bytes)?;
+        let line_offset = self.offset;
+        self.offset += num_bytes as u64;
+        match std::str::from_utf8(&self.bytes) {
+            Ok(line) => buffer.push_str(line),
+            Err(error) => {
+                let position = error.valid_up_to();
+                let context_end = (position + CONTEXT_BYTES).min(num_bytes);
+                let context = &self.bytes[position.saturating_sub(CONTEXT_BYTES)..context_end];
+                let message = format!(
+                    "Invalid UTF-8 at byte offset {}: {}",
+                    line_offset + position as u64,
+                    escape_invalid(context).trim_end_matches(['\n', '\r'])
+                );
+                if !self.continue_on_invalid {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                }
+                tracing::warn!("{message}");
+                buffer.push_str(&String::from_utf8_lossy(&self.bytes));
+            }
+        }
+        // The size of the line is the size in the input, even if the replacement characters are longer.
+        Ok(num_bytes)
+    }
+}
+
+/// Shows the bytes that aren't valid UTF-8 as `\xNN`, keeping the rest as it is.
+fn escape_invalid(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for chunk in bytes.utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{byte:02x}"));
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_byte_offset_is_reported() {
+        let content = b"{\"type\":\"A\"}\n{\"type\":\"caf\xe9\"}\n{\"type\":\"B\"}\n";
+        let mut sut = Utf8Check::new(content.as_slice(), 100, false);
+        let mut buffer = String::new();
+        assert_eq!(13, sut.read_line(&mut buffer).unwrap());
+        let error = sut.read_line(&mut buffer).unwrap_err();
+        assert_eq!("Invalid UTF-8 at byte offset 125: {\"type\":\"caf\\xe9\"}", error.to_string());
+
+        let mut sut = Utf8Check::new(content.as_slice(), 0, true);
+        let mut lines = Vec::new();
+        while sut.read_line(&mut buffer).unwrap() > 0 {
+            lines.push(std::mem::take(&mut buffer));
+        }
+        assert_eq!("{\"type\":\"caf\u{fffd}\"}\n", lines[1]);
+        assert_eq!(3, lines.len());
+    }
+}
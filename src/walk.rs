@@ -0,0 +1,63 @@
+//! Expansion of directory inputs into the files under them, for `--recursive`.
+
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+/// Replaces every directory in `inputs` by the files under it, sorted by path so the output is reproducible. Symlinks
+/// are followed, but a link back to one of its own ancestors is skipped instead of walked forever.
+pub fn expand(inputs: Vec<String>, extensions: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        if input == crate::STDIN_INPUT || !Path::new(&input).is_dir() {
+            expanded.push(input);
+            continue;
+        }
+        for entry in WalkDir::new(&input).follow_links(true).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) if error.loop_ancestor().is_some() => {
+                    tracing::warn!("Skipping symlink loop: {error}");
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+            if !entry.file_type().is_file() || !has_extension(entry.path(), extensions) {
+                continue;
+            }
+            match entry.into_path().into_os_string().into_string() {
+                Ok(path) => expanded.push(path),
+                Err(path) => tracing::warn!("Skipping non UTF-8 path {}", path.to_string_lossy()),
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    extensions.is_empty()
+        || path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extensions.iter().any(|wanted| wanted.trim_start_matches('.') == extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("b");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::write(dir.path().join("a.log"), "").unwrap();
+        std::fs::write(nested.join("c.log"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path(), nested.join("loop")).unwrap();
+        let root = dir.path().to_str().unwrap().to_string();
+        let sut = expand(vec![root.clone()], &[]).unwrap();
+        let expected = vec![format!("{root}/a.log"), format!("{root}/b/c.log")];
+        assert_eq!(expected, sut);
+    }
+}
@@ -0,0 +1,29 @@
+//! `--clipboard` has to outlive the run, which can only be checked from another process.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+#[test]
+fn copied_stats_outlive_the_run() {
+    // Only meaningful where there is a clipboard, CI machines usually don't have one.
+    let Ok(mut clipboard) = arboard::Clipboard::new() else {
+        return;
+    };
+    let small_log = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/small.log");
+    let output = Command::new(env!("CARGO_BIN_EXE_word-counter"))
+        .args(["--clipboard", "--format", "json"])
+        .arg(small_log)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stats = String::from_utf8(output.stdout).unwrap();
+    // The holder takes over the clipboard in the background, give it some time.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while clipboard.get_text().ok().as_deref() != Some(&stats) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(stats, clipboard.get_text().unwrap());
+    // Replacing the content lets the holder exit.
+    clipboard.set_text("").unwrap();
+}
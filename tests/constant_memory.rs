@@ -0,0 +1,33 @@
+//! The RSS checked by `--assert-constant-memory` covers the whole process, so it's measured on a run of its own.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+#[cfg(target_os = "linux")]
+fn memory_stays_bounded_with_few_keys() {
+    let mut run = Command::new(env!("CARGO_BIN_EXE_word-counter"))
+        .args(["--assert-constant-memory", "--memory-bound-mb", "64", "--format", "json", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // ~200MB of input, far beyond the bound, so buffering it would trip the guard. It's generated on the fly, the test
+    // only holds a chunk of lines at a time.
+    let mut stdin = run.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        let payload = "x".repeat(200);
+        for chunk in 0..1_000 {
+            let lines: String = (0..1_000)
+                .map(|line| format!("{{\"type\":\"T{}\",\"payload\":\"{payload}\"}}\n", (chunk * 1_000 + line) % 3))
+                .collect();
+            stdin.write_all(lines.as_bytes()).unwrap();
+        }
+    });
+    let output = run.wait_with_output().unwrap();
+    writer.join().unwrap();
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let counts: Vec<_> = ["T0", "T1", "T2"].iter().map(|object_type| stats[object_type]["count"].as_u64()).collect();
+    assert_eq!(vec![Some(333_334), Some(333_333), Some(333_333)], counts);
+}